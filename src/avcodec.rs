@@ -7,13 +7,17 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use libc::{c_double, c_int, c_uint, c_void};
+use libc::{c_double, c_int, c_uint, c_void, size_t};
+use std::cell::Cell;
 use std::marker::PhantomData;
 use std::ffi::CString;
 use std::i32;
 use std::mem;
+use std::ptr;
+use std::slice;
+use std::sync::Arc;
 
-use avutil::{ AvDictionary, AvFrame };
+use avutil::{ AvDictionary, AvFrame, ColorPrimaries, ColorTransferCharacteristic, Options, SideData, from_cstr };
 use ffi;
 
 pub type AvCodecId = ffi::AVCodecID;
@@ -23,6 +27,22 @@ pub const AV_CODEC_ID_AAC: AvCodecId = 0x15000 + 2;
 
 pub const ERROR_CSTRING: c_int = -1;
 
+/// The ASS header used for text subtitles when none is given, matching the one `libavcodec`
+/// generates for its own text subtitle decoders.
+pub const DEFAULT_ASS_HEADER: &'static str = "[Script Info]\r\n\
+ScriptType: v4.00+\r\n\
+PlayResX: 384\r\n\
+PlayResY: 288\r\n\
+\r\n\
+[V4+ Styles]\r\n\
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, \
+Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, \
+Alignment, MarginL, MarginR, MarginV, Encoding\r\n\
+Style: Default,Arial,16,&Hffffff,&Hffffff,&H0,&H0,0,0,0,0,100,100,0,0,1,1,0,2,10,10,10,0\r\n\
+\r\n\
+[Events]\r\n\
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text\r\n";
+
 pub fn init() {
     unsafe {
         ffi::avcodec_register_all()
@@ -41,10 +61,7 @@ pub struct AvCodec {
 }
 
 impl AvCodec {
-    pub fn find_decoder(codec_id: AvCodecId) -> Result<AvCodec,()> {
-        let codec = unsafe {
-            ffi::avcodec_find_decoder(codec_id)
-        };
+    fn from_ptr(codec: *mut ffi::AVCodec) -> Result<AvCodec,()> {
         if !codec.is_null() {
             Ok(AvCodec {
                 codec: codec,
@@ -53,31 +70,235 @@ impl AvCodec {
             Err(())
         }
     }
+
+    pub fn find_decoder(codec_id: AvCodecId) -> Result<AvCodec,()> {
+        AvCodec::from_ptr(unsafe {
+            ffi::avcodec_find_decoder(codec_id)
+        })
+    }
+
+    pub fn find_encoder(codec_id: AvCodecId) -> Result<AvCodec,()> {
+        AvCodec::from_ptr(unsafe {
+            ffi::avcodec_find_encoder(codec_id)
+        })
+    }
+
+    /// Finds a specific decoder implementation, e.g. `libvpx` rather than the native `vp8`.
+    pub fn find_decoder_by_name(name: &str) -> Result<AvCodec,()> {
+        let name = try!(CString::new(name).or_else(|_| Err(())));
+        AvCodec::from_ptr(unsafe {
+            ffi::avcodec_find_decoder_by_name(name.as_ptr())
+        })
+    }
+
+    pub fn find_encoder_by_name(name: &str) -> Result<AvCodec,()> {
+        let name = try!(CString::new(name).or_else(|_| Err(())));
+        AvCodec::from_ptr(unsafe {
+            ffi::avcodec_find_encoder_by_name(name.as_ptr())
+        })
+    }
+
+    pub fn name(&self) -> String {
+        unsafe {
+            from_cstr((*self.codec).name).unwrap_or(String::new())
+        }
+    }
+
+    /// NB: This is `None` when FFmpeg was built with `--enable-small`.
+    pub fn long_name(&self) -> Option<String> {
+        unsafe {
+            from_cstr((*self.codec).long_name)
+        }
+    }
+
+    /// One of the `AVMEDIA_TYPE_*` constants.
+    pub fn media_type(&self) -> c_int {
+        unsafe {
+            (*self.codec).type_
+        }
+    }
+
+    pub fn id(&self) -> AvCodecId {
+        unsafe {
+            (*self.codec).id
+        }
+    }
+
+    pub fn is_decoder(&self) -> bool {
+        unsafe {
+            ffi::av_codec_is_decoder(self.codec) != 0
+        }
+    }
+
+    pub fn is_encoder(&self) -> bool {
+        unsafe {
+            ffi::av_codec_is_encoder(self.codec) != 0
+        }
+    }
+
+    /// A combination of the `CODEC_CAP_*` flags.
+    pub fn capabilities(&self) -> c_int {
+        unsafe {
+            (*self.codec).capabilities
+        }
+    }
+
+    /// An empty list means the codec does not restrict its input.
+    pub fn supported_sample_rates(&self) -> Vec<c_int> {
+        unsafe {
+            terminated_list((*self.codec).supported_samplerates, 0)
+        }
+    }
+
+    pub fn pixel_formats(&self) -> Vec<c_int> {
+        unsafe {
+            terminated_list((*self.codec).pix_fmts, ffi::AV_PIX_FMT_NONE)
+        }
+    }
+
+    pub fn sample_formats(&self) -> Vec<ffi::AVSampleFormat> {
+        unsafe {
+            terminated_list((*self.codec).sample_fmts, ffi::AV_SAMPLE_FMT_NONE)
+        }
+    }
+
+    pub fn channel_layouts(&self) -> Vec<u64> {
+        unsafe {
+            terminated_list((*self.codec).channel_layouts, 0)
+        }
+    }
+
+    pub fn profiles(&self) -> Vec<(c_int, String)> {
+        let mut profiles = Vec::new();
+        unsafe {
+            let mut profile = (*self.codec).profiles;
+            if profile.is_null() {
+                return profiles
+            }
+            while (*profile).profile != ffi::FF_PROFILE_UNKNOWN {
+                profiles.push(((*profile).profile, from_cstr((*profile).name).unwrap_or(String::new())));
+                profile = profile.offset(1);
+            }
+        }
+        profiles
+    }
+}
+
+unsafe fn terminated_list<T: Copy + PartialEq>(list: *const T, terminator: T) -> Vec<T> {
+    let mut values = Vec::new();
+    if list.is_null() {
+        return values
+    }
+    let mut i = 0;
+    while *list.offset(i) != terminator {
+        values.push(*list.offset(i));
+        i += 1;
+    }
+    values
+}
+
+/// Iterates over every decoder and encoder registered with `libavcodec`.
+///
+/// Call `init` first, or the list will be empty.
+pub fn codecs() -> Codecs {
+    Codecs {
+        prev: ptr::null_mut(),
+    }
+}
+
+pub struct Codecs {
+    prev: *mut ffi::AVCodec,
+}
+
+impl Iterator for Codecs {
+    type Item = AvCodec;
+
+    fn next(&mut self) -> Option<AvCodec> {
+        unsafe {
+            self.prev = ffi::av_codec_next(self.prev);
+        }
+        if self.prev.is_null() {
+            None
+        } else {
+            Some(AvCodec {
+                codec: self.prev,
+            })
+        }
+    }
 }
 
-pub struct AvCodecContext {
+/// A codec context, which is either owned by this wrapper (from `new`) and freed along with it,
+/// or borrowed from a stream of an `AvFormatContext` that lives for `'a`.
+///
+/// Borrowed contexts that were opened through this wrapper are closed when it is dropped, but
+/// are otherwise left to the format context to free.
+pub struct AvCodecContext<'a> {
     pub context: ffi::EitherAVCodecContext,
-    extra_data: Option<Vec<u8>>,
+    owned: bool,
+    opened: Cell<bool>,
+    buffer_hooks: Option<Box<BufferHooks>>,
+    _marker: PhantomData<&'a ffi::AVCodecContext>,
 }
 
-impl AvCodecContext {
-    pub fn new(codec: &AvCodec) -> AvCodecContext {
+#[unsafe_destructor]
+impl<'a> Drop for AvCodecContext<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            if self.owned {
+                let mut context = self.context.ptr();
+                ffi::avcodec_free_context(&mut context);
+                return
+            }
+
+            if self.opened.get() {
+                ffi::avcodec_close(self.context.ptr());
+            }
+            if self.buffer_hooks.is_some() {
+                // The context outlives this wrapper, so it must not be left pointing at the
+                // hooks.
+                *ffmpeg_ffi_avcodeccontext_field!(self.context, mut opaque) = ptr::null_mut();
+                *ffmpeg_ffi_avcodeccontext_field!(self.context, mut get_buffer2) = default_get_buffer2;
+            }
+        }
+    }
+}
+
+// A context may be used from any thread, one at a time. Only `'static` contexts qualify, since a
+// borrowed one must stay with the `AvFormatContext` it belongs to.
+unsafe impl Send for AvCodecContext<'static> {}
+
+impl<'a> AvCodecContext<'a> {
+    pub fn new(codec: &AvCodec) -> AvCodecContext<'a> {
         unsafe {
             let context = ffi::avcodec_alloc_context3(codec.codec);
+            assert!(!context.is_null());
             AvCodecContext {
                 context: ffi::EitherAVCodecContext::from_ptr(context),
-                extra_data: None,
+                owned: true,
+                opened: Cell::new(false),
+                buffer_hooks: None,
+                _marker: PhantomData,
             }
         }
     }
 
-    pub fn from_raw(context: *mut ffi::AVCodecContext) -> AvCodecContext {
+    /// Wraps a context owned by something else, such as a stream's `codec`, which must outlive
+    /// `'a`. Nothing ties `'a` to the owner, so the caller must pick it (and never `'static` for
+    /// a borrowed context, which would make it `Send`).
+    pub unsafe fn from_raw(context: *mut ffi::AVCodecContext) -> AvCodecContext<'a> {
         AvCodecContext {
             context: ffi::EitherAVCodecContext::from_ptr(context),
-            extra_data: None,
+            owned: false,
+            opened: Cell::new(false),
+            buffer_hooks: None,
+            _marker: PhantomData,
         }
     }
 
+    pub fn is_owned(&self) -> bool {
+        self.owned
+    }
+
     pub fn open(&self, codec: &AvCodec, options: AvDictionary) -> (Result<(),()>, AvDictionary) {
         // The memory management that `libavcodec` expects around the `options` argument is really
         // weird.
@@ -91,26 +312,55 @@ impl AvCodecContext {
             dictionary: options_not_found,
         };
         if result == 0 {
+            self.opened.set(true);
             (Ok(()), options_not_found)
         } else {
             (Err(()), options_not_found)
         }
     }
 
-    pub fn set_extra_data(&mut self, mut extra_data: Vec<u8>) {
+    /// Copies `extra_data` into the context, which frees it along with the context.
+    pub fn set_extra_data(&mut self, extra_data: &[u8]) {
         assert!(extra_data.len() <= (i32::MAX as usize));
         unsafe {
-            *ffmpeg_ffi_avcodeccontext_field!(self.context, mut extradata) = extra_data.as_mut_slice().as_mut_ptr();
+            let copy = ffi::av_mallocz((extra_data.len() + ffi::FF_INPUT_BUFFER_PADDING_SIZE) as size_t) as *mut u8;
+            assert!(!copy.is_null());
+            slice::from_raw_parts_mut(copy, extra_data.len()).clone_from_slice(extra_data);
+
+            let old = *ffmpeg_ffi_avcodeccontext_field!(self.context, extradata);
+            ffi::av_free(old as *mut c_void);
+            *ffmpeg_ffi_avcodeccontext_field!(self.context, mut extradata) = copy;
             *ffmpeg_ffi_avcodeccontext_field!(self.context, mut extradata_size) = extra_data.len() as i32;
         }
-        self.extra_data = Some(extra_data);
     }
 
-    pub fn set_get_buffer_callback(&mut self, callback: Box<FnMut(&AvFrame)>) {
-        unsafe {
-            *ffmpeg_ffi_avcodeccontext_field!(self.context, mut opaque) = mem::transmute::<_,*mut c_void>(Box::new(callback));
-            *ffmpeg_ffi_avcodeccontext_field!(self.context, mut get_buffer) = get_buffer;
+    /// Calls `callback` with every frame the decoder allocates, before it decodes into it.
+    pub fn set_get_buffer_callback(&mut self, callback: Box<FnMut(&AvFrame) + Send>) {
+        self.buffer_hooks().callback = Some(callback);
+    }
+
+    /// Has the decoder write frames into buffers from `allocator` rather than its own pool.
+    /// Must be called before `open`.
+    ///
+    /// Decoders that can't decode into user buffers (those without `CODEC_CAP_DR1`) keep using
+    /// their own, as does every frame `allocator` declines to allocate.
+    pub fn set_frame_buffer_allocator(&mut self, allocator: Arc<FrameBufferAllocator>) {
+        self.buffer_hooks().allocator = Some(allocator);
+    }
+
+    fn buffer_hooks(&mut self) -> &mut BufferHooks {
+        if self.buffer_hooks.is_none() {
+            let mut hooks = Box::new(BufferHooks {
+                callback: None,
+                allocator: None,
+            });
+            unsafe {
+                *ffmpeg_ffi_avcodeccontext_field!(self.context, mut opaque) = &mut *hooks as *mut BufferHooks as *mut c_void;
+                *ffmpeg_ffi_avcodeccontext_field!(self.context, mut get_buffer2) = get_buffer2;
+            }
+            self.buffer_hooks = Some(hooks);
         }
+        self.buffer_hooks.as_mut().unwrap()
     }
 
     pub fn decode_video(&self, picture: &AvFrame, packet: &mut AvPacket) -> Result<bool,()> {
@@ -149,6 +399,56 @@ impl AvCodecContext {
         }
     }
 
+    /// Returns `Ok(None)` if the packet was consumed without completing a subtitle.
+    pub fn decode_subtitle(&self, packet: &mut AvPacket) -> Result<Option<AvSubtitle>,c_int> {
+        let mut subtitle = AvSubtitle::new();
+        let mut got_subtitle = 0;
+        let result = unsafe {
+            ffi::avcodec_decode_subtitle2(self.context.ptr(),
+                                          &mut subtitle.subtitle,
+                                          &mut got_subtitle,
+                                          packet.packet.ptr())
+        };
+        if result < 0 {
+            Err(result)
+        } else if got_subtitle != 0 {
+            Ok(Some(subtitle))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Encodes into `buf`, returning the number of bytes written.
+    pub fn encode_subtitle(&self, subtitle: &AvSubtitle, buf: &mut [u8]) -> Result<usize,c_int> {
+        assert!(buf.len() <= (i32::MAX as usize));
+        let result = unsafe {
+            ffi::avcodec_encode_subtitle(self.context.ptr(), buf.as_mut_ptr(), buf.len() as c_int, &subtitle.subtitle)
+        };
+        if result >= 0 {
+            Ok(result as usize)
+        } else {
+            Err(result)
+        }
+    }
+
+    /// Sets the ASS header that text subtitle encoders parse their events against (and that the
+    /// `ass` encoder stores as extradata). Must be called before `open`.
+    pub fn set_subtitle_header(&self, header: &str) -> Result<(),c_int> {
+        unsafe {
+            // Freed by `libavcodec` along with the context, so it must come from `av_malloc`.
+            let subtitle_header = ffi::av_mallocz((header.len() + 1) as size_t) as *mut u8;
+            if subtitle_header.is_null() {
+                return Err(ffi::AVERROR_ENOMEM)
+            }
+            slice::from_raw_parts_mut(subtitle_header, header.len()).clone_from_slice(header.as_bytes());
+
+            ffi::av_free(*ffmpeg_ffi_avcodeccontext_field!(self.context, subtitle_header) as *mut c_void);
+            *ffmpeg_ffi_avcodeccontext_field!(self.context, mut subtitle_header) = subtitle_header;
+            *ffmpeg_ffi_avcodeccontext_field!(self.context, mut subtitle_header_size) = header.len() as c_int;
+        }
+        Ok(())
+    }
+
     pub fn set_pkt_timebase(&self, timebase: &ffi::AVRational) {
         unsafe {
             ffi::av_codec_set_pkt_timebase(self.context.ptr(), *timebase)
@@ -198,27 +498,333 @@ impl AvCodecContext {
             *ffmpeg_ffi_avcodeccontext_field!(self.context, channels)
         }
     }
+
+    // `AVFrame` carries no primaries or transfer characteristic in the versions these bindings
+    // support, so they come from the decoder.
+
+    pub fn color_primaries(&self) -> Option<ColorPrimaries> {
+        unsafe {
+            ColorPrimaries::from_raw(*ffmpeg_ffi_avcodeccontext_field!(self.context, color_primaries))
+        }
+    }
+
+    pub fn color_transfer_characteristic(&self) -> Option<ColorTransferCharacteristic> {
+        unsafe {
+            let color_trc = *ffmpeg_ffi_avcodeccontext_field!(self.context, color_trc);
+            ColorTransferCharacteristic::from_raw(color_trc)
+        }
+    }
 }
 
-extern "C" fn get_buffer(context: *mut ffi::AVCodecContext, frame: *mut ffi::AVFrame) -> c_int {
-    let result = unsafe {
-        ffi::avcodec_default_get_buffer(context, frame)
-    };
-    let frame = AvFrame {
-        frame: frame,
-    };
+impl<'a> Options for AvCodecContext<'a> {
+    fn as_options_ptr(&self) -> *mut c_void {
+        self.context.ptr() as *mut c_void
+    }
+}
+
+/// Decoder settings that must be in place before the codec context is opened, applied through
+/// the options dictionary passed to `AvCodecContext::open` or `AvFormatContext::open_stream`.
+///
+/// Anything left unset keeps the codec's default.
+pub struct DecoderOptions {
+    threads: Option<usize>,
+    thread_type: Option<c_int>,
+    skip_frame: Option<ffi::AVDiscard>,
+    skip_loop_filter: Option<ffi::AVDiscard>,
+    skip_idct: Option<ffi::AVDiscard>,
+    lowres: Option<c_int>,
+    error_concealment: Option<c_int>,
+}
+
+impl DecoderOptions {
+    pub fn new() -> DecoderOptions {
+        DecoderOptions {
+            threads: None,
+            thread_type: None,
+            skip_frame: None,
+            skip_loop_filter: None,
+            skip_idct: None,
+            lowres: None,
+            error_concealment: None,
+        }
+    }
+
+    /// The number of decoding threads; 0 lets FFmpeg pick one per CPU core.
+    pub fn threads(mut self, threads: usize) -> DecoderOptions {
+        self.threads = Some(threads);
+        self
+    }
+
+    /// Decodes several frames at once, at the cost of one frame of latency per thread.
+    pub fn frame_threading(mut self) -> DecoderOptions {
+        self.thread_type = Some(ffi::FF_THREAD_FRAME);
+        self
+    }
+
+    /// Splits each frame between threads, which adds no latency but only helps with streams
+    /// encoded with multiple slices.
+    pub fn slice_threading(mut self) -> DecoderOptions {
+        self.thread_type = Some(ffi::FF_THREAD_SLICE);
+        self
+    }
+
+    /// Lets the decoder use whichever of frame and slice threading it supports.
+    pub fn frame_and_slice_threading(mut self) -> DecoderOptions {
+        self.thread_type = Some(ffi::FF_THREAD_FRAME | ffi::FF_THREAD_SLICE);
+        self
+    }
+
+    /// Skips decoding frames matching one of the `AVDISCARD_*` levels, e.g. `AVDISCARD_NONKEY`
+    /// to only decode keyframes for thumbnails.
+    pub fn skip_frame(mut self, discard: ffi::AVDiscard) -> DecoderOptions {
+        self.skip_frame = Some(discard);
+        self
+    }
+
+    pub fn skip_loop_filter(mut self, discard: ffi::AVDiscard) -> DecoderOptions {
+        self.skip_loop_filter = Some(discard);
+        self
+    }
+
+    pub fn skip_idct(mut self, discard: ffi::AVDiscard) -> DecoderOptions {
+        self.skip_idct = Some(discard);
+        self
+    }
+
+    /// Decodes at 1/2, 1/4 or 1/8 resolution (`lowres` of 1, 2 or 3), for decoders that
+    /// support it.
+    pub fn lowres(mut self, lowres: c_int) -> DecoderOptions {
+        self.lowres = Some(lowres);
+        self
+    }
+
+    /// A combination of the `FF_EC_*` error concealment flags.
+    pub fn error_concealment(mut self, flags: c_int) -> DecoderOptions {
+        self.error_concealment = Some(flags);
+        self
+    }
+
+    pub fn to_dictionary(&self) -> AvDictionary {
+        let mut dictionary = AvDictionary::new();
+        self.apply(&mut dictionary);
+        dictionary
+    }
+
+    /// Adds these settings to `dictionary`, replacing any it already has.
+    pub fn apply(&self, dictionary: &mut AvDictionary) {
+        if let Some(threads) = self.threads {
+            if threads == 0 {
+                dictionary.set("threads", "auto");
+            } else {
+                dictionary.set("threads", &threads.to_string());
+            }
+        }
+        let settings = [
+            ("thread_type", self.thread_type),
+            ("skip_frame", self.skip_frame),
+            ("skip_loop_filter", self.skip_loop_filter),
+            ("skip_idct", self.skip_idct),
+            ("lowres", self.lowres),
+            ("ec", self.error_concealment),
+        ];
+        for &(key, value) in settings.iter() {
+            if let Some(value) = value {
+                dictionary.set(key, &value.to_string());
+            }
+        }
+    }
+}
+
+/// Memory that a decoder writes a frame into.
+pub trait FrameBuffer: Send {
+    fn data(&mut self) -> &mut [u8];
+}
+
+impl FrameBuffer for Vec<u8> {
+    fn data(&mut self) -> &mut [u8] {
+        &mut self[..]
+    }
+}
+
+/// Supplies the memory decoded frames are written into, e.g. from a pool or a region that can
+/// be uploaded to the GPU directly.
+///
+/// Buffers are released once the last frame referencing them is freed, which may happen after
+/// the codec context is gone and on another thread.
+pub trait FrameBufferAllocator: Send + Sync {
+    /// Returns a buffer of at least `size` bytes, or `None` to have the decoder allocate the
+    /// frame itself.
+    fn allocate(&self, size: usize) -> Option<Box<FrameBuffer>>;
+
+    /// Takes back a buffer once FFmpeg no longer uses it.
+    fn release(&self, buffer: Box<FrameBuffer>) {
+        drop(buffer)
+    }
+}
+
+/// The alignment of the planes handed to decoders, enough for any SIMD FFmpeg uses.
+const FRAME_BUFFER_ALIGN: usize = 64;
+
+struct BufferHooks {
+    callback: Option<Box<FnMut(&AvFrame) + Send>>,
+    allocator: Option<Arc<FrameBufferAllocator>>,
+}
+
+extern "C" fn default_get_buffer2(context: *mut ffi::AVCodecContext, frame: *mut ffi::AVFrame, flags: c_int) -> c_int {
     unsafe {
-        let mut callback = if version() < 0x380d64 {
-            let context = mem::transmute::<_,*mut ffi::AVCodecContextV362300>(context);
-            mem::transmute::<*mut c_void,Box<Box<FnMut(&AvFrame)>>>((*context).opaque)
+        ffi::avcodec_default_get_buffer2(context, frame, flags)
+    }
+}
+
+extern "C" fn get_buffer2(context: *mut ffi::AVCodecContext, frame: *mut ffi::AVFrame, flags: c_int) -> c_int {
+    unsafe {
+        let either_context = ffi::EitherAVCodecContext::from_ptr(context);
+        let hooks = &mut *(*ffmpeg_ffi_avcodeccontext_field!(either_context, opaque) as *mut BufferHooks);
+
+        let codec = *ffmpeg_ffi_avcodeccontext_field!(either_context, codec);
+        let direct_rendering = !codec.is_null() && (*codec).capabilities & ffi::CODEC_CAP_DR1 != 0;
+        let allocated = match hooks.allocator {
+            Some(ref allocator) if direct_rendering => {
+                match *ffmpeg_ffi_avcodeccontext_field!(either_context, codec_type) {
+                    ffi::AVMEDIA_TYPE_VIDEO => allocate_video_frame(context, allocator, frame),
+                    ffi::AVMEDIA_TYPE_AUDIO => allocate_audio_frame(allocator, frame),
+                    _ => false,
+                }
+            }
+            _ => false,
+        };
+        let result = if allocated {
+            0
         } else {
-            let context = mem::transmute::<_,*mut ffi::AVCodecContextV380D64>(context);
-            mem::transmute::<*mut c_void,Box<Box<FnMut(&AvFrame)>>>((*context).opaque)
+            ffi::avcodec_default_get_buffer2(context, frame, flags)
         };
-        (*callback)(&frame);
-        mem::forget(frame);
+
+        if result >= 0 {
+            if let Some(ref mut callback) = hooks.callback {
+                let frame = AvFrame {
+                    frame: frame,
+                };
+                (*callback)(&frame);
+                mem::forget(frame);
+            }
+        }
+        result
+    }
+}
+
+unsafe fn allocate_video_frame(context: *mut ffi::AVCodecContext,
+                               allocator: &Arc<FrameBufferAllocator>,
+                               frame: *mut ffi::AVFrame)
+                               -> bool {
+    let (mut width, mut height) = ((*frame).width, (*frame).height);
+    let mut linesize_align = [0; ffi::AV_NUM_DATA_POINTERS];
+    ffi::avcodec_align_dimensions2(context, &mut width, &mut height, linesize_align.as_mut_ptr());
+
+    let mut linesizes = [0; 4];
+    if ffi::av_image_fill_linesizes(linesizes.as_mut_ptr(), (*frame).format, width) < 0 {
+        return false
     }
-    result
+    for (linesize, &align) in linesizes.iter_mut().zip(linesize_align.iter()) {
+        let align = if align > FRAME_BUFFER_ALIGN as c_int { align } else { FRAME_BUFFER_ALIGN as c_int };
+        *linesize = (*linesize + align - 1) / align * align;
+    }
+
+    // With a null base this yields each plane's offset and the total size.
+    let mut offsets = [ptr::null_mut(); 4];
+    let size = ffi::av_image_fill_pointers(offsets.as_mut_ptr(),
+                                           (*frame).format,
+                                           height,
+                                           ptr::null_mut(),
+                                           linesizes.as_ptr());
+    if size < 0 {
+        return false
+    }
+
+    // Some decoders read a little past the end of the last line.
+    let base = match allocate_frame_buffer(allocator, frame, size as usize + 16) {
+        Some(base) => base,
+        None => return false,
+    };
+    for i in 0..4 {
+        if i == 0 || !offsets[i].is_null() {
+            (*frame).data[i] = base.offset(offsets[i] as isize);
+            (*frame).linesize[i] = linesizes[i];
+        }
+    }
+    (*frame).extended_data = (*frame).data.as_mut_ptr();
+    true
+}
+
+unsafe fn allocate_audio_frame(allocator: &Arc<FrameBufferAllocator>, frame: *mut ffi::AVFrame) -> bool {
+    let channels = ffi::av_frame_get_channels(frame);
+    let planes = if ffi::av_sample_fmt_is_planar((*frame).format) != 0 { channels } else { 1 };
+    if planes as usize > ffi::AV_NUM_DATA_POINTERS {
+        // These need `extended_data` allocated separately, so leave them to FFmpeg.
+        return false
+    }
+
+    let mut linesize = 0;
+    let size = ffi::av_samples_get_buffer_size(&mut linesize,
+                                               channels,
+                                               (*frame).nb_samples,
+                                               (*frame).format,
+                                               FRAME_BUFFER_ALIGN as c_int);
+    if size < 0 {
+        return false
+    }
+
+    let base = match allocate_frame_buffer(allocator, frame, size as usize) {
+        Some(base) => base,
+        None => return false,
+    };
+    ffi::av_samples_fill_arrays((*frame).data.as_mut_ptr(),
+                                &mut linesize,
+                                base,
+                                channels,
+                                (*frame).nb_samples,
+                                (*frame).format,
+                                FRAME_BUFFER_ALIGN as c_int);
+    (*frame).linesize[0] = linesize;
+    (*frame).extended_data = (*frame).data.as_mut_ptr();
+    true
+}
+
+/// Allocates `size` aligned bytes from `allocator` and hands them to the frame as its only
+/// buffer reference, returning the start of the usable memory.
+unsafe fn allocate_frame_buffer(allocator: &Arc<FrameBufferAllocator>, frame: *mut ffi::AVFrame, size: usize)
+                                -> Option<*mut u8> {
+    let mut buffer = match allocator.allocate(size + FRAME_BUFFER_ALIGN - 1) {
+        Some(buffer) => buffer,
+        None => return None,
+    };
+    let (start, len) = {
+        let data = buffer.data();
+        (data.as_mut_ptr(), data.len())
+    };
+    let padding = (FRAME_BUFFER_ALIGN - (start as usize) % FRAME_BUFFER_ALIGN) % FRAME_BUFFER_ALIGN;
+    if len < size + padding || size > (i32::MAX as usize) {
+        allocator.release(buffer);
+        return None
+    }
+
+    // Moving the box leaves the buffer itself where it is.
+    let base = start.offset(padding as isize);
+    let opaque = mem::transmute::<Box<(Arc<FrameBufferAllocator>, Box<FrameBuffer>)>,*mut c_void>(
+        Box::new((allocator.clone(), buffer)));
+    let buffer_ref = ffi::av_buffer_create(base, size as c_int, free_frame_buffer, opaque, 0);
+    if buffer_ref.is_null() {
+        free_frame_buffer(opaque, base);
+        return None
+    }
+    (*frame).buf[0] = buffer_ref;
+    Some(base)
+}
+
+extern "C" fn free_frame_buffer(opaque: *mut c_void, _: *mut u8) {
+    let (allocator, buffer) = unsafe {
+        *mem::transmute::<*mut c_void,Box<(Arc<FrameBufferAllocator>, Box<FrameBuffer>)>>(opaque)
+    };
+    allocator.release(buffer);
 }
 
 pub struct AvPacket<'a> {
@@ -226,6 +832,9 @@ pub struct AvPacket<'a> {
     _marker: PhantomData<&'a AvPacket<'a>>,
 }
 
+// Any data a packet borrows is `&'a mut [u8]`, which is `Send`; anything else is refcounted.
+unsafe impl<'a> Send for AvPacket<'a> {}
+
 impl<'a> AvPacket<'a> {
     /// NB: `FF_INPUT_BUFFER_PADDING_SIZE` bytes of data at the end of the slice are ignored!
     pub fn new(data: &'a mut [u8]) -> AvPacket<'a> {
@@ -263,4 +872,972 @@ impl<'a> AvPacket<'a> {
     pub fn has_data(&self) -> bool {
         *ffmpeg_ffi_avpacket_field!(self.packet, size) > 0
     }
+
+    pub fn pts(&self) -> i64 {
+        *ffmpeg_ffi_avpacket_field!(self.packet, pts)
+    }
+
+    pub fn dts(&self) -> i64 {
+        *ffmpeg_ffi_avpacket_field!(self.packet, dts)
+    }
+
+    pub fn duration(&self) -> c_int {
+        *ffmpeg_ffi_avpacket_field!(self.packet, duration)
+    }
+
+    pub fn stream_index(&self) -> usize {
+        *ffmpeg_ffi_avpacket_field!(self.packet, stream_index) as usize
+    }
+
+    pub fn data<'b>(&'b self) -> &'b [u8] {
+        let data = *ffmpeg_ffi_avpacket_field!(self.packet, data);
+        let size = *ffmpeg_ffi_avpacket_field!(self.packet, size);
+        if data.is_null() || size <= 0 {
+            &[]
+        } else {
+            unsafe {
+                slice::from_raw_parts(data as *const u8, size as usize)
+            }
+        }
+    }
+
+    /// A combination of the `AV_PKT_FLAG_*` flags.
+    pub fn flags(&self) -> c_int {
+        *ffmpeg_ffi_avpacket_field!(self.packet, flags)
+    }
+
+    pub fn is_key_frame(&self) -> bool {
+        self.flags() & ffi::AV_PKT_FLAG_KEY != 0
+    }
+
+    pub fn set_pts(&mut self, pts: i64) {
+        *ffmpeg_ffi_avpacket_field!(self.packet, mut pts) = pts;
+    }
+
+    pub fn set_dts(&mut self, dts: i64) {
+        *ffmpeg_ffi_avpacket_field!(self.packet, mut dts) = dts;
+    }
+
+    pub fn set_duration(&mut self, duration: c_int) {
+        *ffmpeg_ffi_avpacket_field!(self.packet, mut duration) = duration;
+    }
+
+    pub fn set_stream_index(&mut self, index: usize) {
+        *ffmpeg_ffi_avpacket_field!(self.packet, mut stream_index) = index as c_int;
+    }
+
+    pub fn set_flags(&mut self, flags: c_int) {
+        *ffmpeg_ffi_avpacket_field!(self.packet, mut flags) = flags;
+    }
+
+    pub fn side_data(&self) -> Vec<SideData> {
+        let list = *ffmpeg_ffi_avpacket_field!(self.packet, side_data);
+        let count = *ffmpeg_ffi_avpacket_field!(self.packet, side_data_elems);
+        unsafe {
+            SideData::from_packet_list(list, count)
+        }
+    }
+
+    /// Releases any data FFmpeg allocated for this packet (e.g. in `AvFormatContext::read_packet`)
+    /// and leaves it empty. Borrowed data passed to `new` is left alone.
+    pub fn clear(&mut self) {
+        unsafe {
+            ffi::av_free_packet(self.packet.ptr());
+        }
+    }
+}
+
+/// A bitstream filter such as `h264_mp4toannexb` or `aac_adtstoasc`, which rewrites packets
+/// without decoding them.
+///
+/// Filters may update the codec context they were created for, e.g. to move parameter sets into
+/// its extradata, so it is borrowed for as long as the filter lives.
+///
+/// FFmpeg 2.x only has the old `av_bitstream_filter_filter` API, which rewrites one buffer into
+/// one buffer. The send/receive calls here follow the shape of the `av_bsf` API that replaced it,
+/// but every packet sent yields at most one packet, and filters that need to split or merge
+/// packets can't be expressed.
+pub struct AvBitstreamFilter<'a> {
+    pub context: *mut ffi::AVBitStreamFilterContext,
+    codec: *mut ffi::AVCodecContext,
+    pending: Option<AvPacket<'static>>,
+    draining: bool,
+    _marker: PhantomData<&'a AvCodecContext<'a>>,
+}
+
+impl<'a> Drop for AvBitstreamFilter<'a> {
+    fn drop(&mut self) {
+        if let Some(ref mut packet) = self.pending {
+            packet.clear()
+        }
+        unsafe {
+            ffi::av_bitstream_filter_close(self.context)
+        }
+    }
+}
+
+impl<'a> AvBitstreamFilter<'a> {
+    /// Creates the named filter for packets produced for `codec`.
+    pub fn new(name: &str, codec: &'a AvCodecContext) -> Result<AvBitstreamFilter<'a>,c_int> {
+        unsafe {
+            AvBitstreamFilter::from_raw_codec(name, codec.context.ptr())
+        }
+    }
+
+    /// `codec` must outlive the filter.
+    pub unsafe fn from_raw_codec(name: &str, codec: *mut ffi::AVCodecContext) -> Result<AvBitstreamFilter<'a>,c_int> {
+        let name = try!(CString::new(name).or_else(|_| Err(ERROR_CSTRING)));
+        let context = ffi::av_bitstream_filter_init(name.as_ptr());
+        if context.is_null() {
+            return Err(-1)
+        }
+        Ok(AvBitstreamFilter {
+            context: context,
+            codec: codec,
+            pending: None,
+            draining: false,
+            _marker: PhantomData,
+        })
+    }
+
+    /// Filters `packet`, or with `None` signals the end of the stream.
+    ///
+    /// Fails with `AVERROR_EAGAIN` while the output of the previous packet hasn't been received,
+    /// and with `AVERROR_EOF` once the end of the stream was signalled.
+    pub fn send_packet(&mut self, packet: Option<&AvPacket>) -> Result<(),c_int> {
+        if self.draining {
+            return Err(ffi::AVERROR_EOF)
+        }
+        if self.pending.is_some() {
+            return Err(ffi::AVERROR_EAGAIN)
+        }
+        let packet = match packet {
+            Some(packet) => packet,
+            None => {
+                self.draining = true;
+                return Ok(())
+            }
+        };
+
+        let mut out_data = ptr::null_mut();
+        let mut out_size = 0;
+        unsafe {
+            let result = ffi::av_bitstream_filter_filter(self.context,
+                                                         self.codec,
+                                                         ptr::null(),
+                                                         &mut out_data,
+                                                         &mut out_size,
+                                                         packet.data().as_ptr(),
+                                                         packet.data().len() as c_int,
+                                                         packet.is_key_frame() as c_int);
+            if result < 0 {
+                return Err(result)
+            }
+
+            // The output is newly allocated if `result` is positive, and otherwise points into the
+            // input or the filter's own state, so it is copied into a packet of its own either way.
+            let output = if out_data.is_null() || out_size <= 0 {
+                Ok(None)
+            } else {
+                copy_packet(packet, slice::from_raw_parts(out_data as *const u8, out_size as usize))
+                    .map(Some)
+            };
+            if result > 0 {
+                ffi::av_free(out_data as *mut c_void);
+            }
+            self.pending = try!(output);
+        }
+        Ok(())
+    }
+
+    /// Returns the next filtered packet, which owns its data; call `clear` on it when done.
+    ///
+    /// Fails with `AVERROR_EAGAIN` if another packet must be sent first, and with `AVERROR_EOF`
+    /// once the end of the stream was signalled and all output received.
+    pub fn receive_packet(&mut self) -> Result<AvPacket<'static>,c_int> {
+        match self.pending.take() {
+            Some(packet) => Ok(packet),
+            None if self.draining => Err(ffi::AVERROR_EOF),
+            None => Err(ffi::AVERROR_EAGAIN),
+        }
+    }
+}
+
+/// Makes a packet owning a copy of `data`, with the timestamps, flags and side data of `props`.
+unsafe fn copy_packet(props: &AvPacket, data: &[u8]) -> Result<AvPacket<'static>,c_int> {
+    let mut packet = AvPacket::empty();
+    let result = ffi::av_new_packet(packet.packet.ptr(), data.len() as c_int);
+    if result < 0 {
+        return Err(result)
+    }
+    ptr::copy_nonoverlapping(data.as_ptr(), *ffmpeg_ffi_avpacket_field!(packet.packet, data), data.len());
+
+    *ffmpeg_ffi_avpacket_field!(packet.packet, mut pts) = *ffmpeg_ffi_avpacket_field!(props.packet, pts);
+    *ffmpeg_ffi_avpacket_field!(packet.packet, mut dts) = *ffmpeg_ffi_avpacket_field!(props.packet, dts);
+    *ffmpeg_ffi_avpacket_field!(packet.packet, mut duration) = *ffmpeg_ffi_avpacket_field!(props.packet, duration);
+    *ffmpeg_ffi_avpacket_field!(packet.packet, mut stream_index) = *ffmpeg_ffi_avpacket_field!(props.packet, stream_index);
+    *ffmpeg_ffi_avpacket_field!(packet.packet, mut flags) = *ffmpeg_ffi_avpacket_field!(props.packet, flags);
+    *ffmpeg_ffi_avpacket_field!(packet.packet, mut pos) = *ffmpeg_ffi_avpacket_field!(props.packet, pos);
+    *ffmpeg_ffi_avpacket_field!(packet.packet, mut convergence_duration) =
+        *ffmpeg_ffi_avpacket_field!(props.packet, convergence_duration);
+
+    let list = *ffmpeg_ffi_avpacket_field!(props.packet, side_data);
+    let count = *ffmpeg_ffi_avpacket_field!(props.packet, side_data_elems);
+    for i in 0..count {
+        let entry = &*list.offset(i as isize);
+        let copy = ffi::av_packet_new_side_data(packet.packet.ptr(), entry.type_, entry.size);
+        if copy.is_null() {
+            packet.clear();
+            return Err(ffi::AVERROR_ENOMEM)
+        }
+        ptr::copy_nonoverlapping(entry.data as *const u8, copy, entry.size as usize);
+    }
+    Ok(packet)
+}
+
+/// Splits a raw elementary stream (e.g. H.264 Annex B, AAC ADTS or MP3) into the packets a decoder
+/// expects, for input that does not come through a demuxer.
+pub struct AvParser {
+    pub context: *mut ffi::AVCodecParserContext,
+    /// The input of the last `parse` call, followed by the padding the parser reads past its end.
+    input: Vec<u8>,
+}
+
+impl Drop for AvParser {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::av_parser_close(self.context)
+        }
+    }
+}
+
+impl AvParser {
+    pub fn new(codec_id: AvCodecId) -> Result<AvParser,()> {
+        let context = unsafe {
+            ffi::av_parser_init(codec_id)
+        };
+        if !context.is_null() {
+            Ok(AvParser {
+                context: context,
+                input: Vec::new(),
+            })
+        } else {
+            Err(())
+        }
+    }
+
+    /// Feeds `data` to the parser, returning how many bytes of it were consumed along with the
+    /// packet completed by them, if any. Call this again with the remaining bytes until all of
+    /// them have been consumed.
+    ///
+    /// `pts` and `dts` (or `ffi::AV_NOPTS_VALUE`) apply to the start of `data`, and are attached
+    /// to the packet beginning there. `codec` must be the context the packets will be decoded
+    /// with, since parsing fills in stream parameters such as the dimensions or sample rate.
+    ///
+    /// `data` needs no padding of its own: it is copied into a buffer with the
+    /// `FF_INPUT_BUFFER_PADDING_SIZE` bytes the parser may read past its end.
+    pub fn parse(&mut self, codec: &AvCodecContext, data: &[u8], pts: i64, dts: i64)
+                 -> Result<(usize, Option<ParsedPacket>),c_int> {
+        assert!(data.len() <= (i32::MAX as usize));
+        self.input.clear();
+        self.input.push_all(data);
+        for _ in 0..ffi::FF_INPUT_BUFFER_PADDING_SIZE {
+            self.input.push(0)
+        }
+        let input = self.input.as_ptr();
+        self.parse_raw(codec, input, data.len() as c_int, pts, dts)
+    }
+
+    /// Signals the end of the stream, returning the last packet still buffered in the parser.
+    pub fn flush(&mut self, codec: &AvCodecContext) -> Result<Option<ParsedPacket>,c_int> {
+        self.parse_raw(codec, ptr::null(), 0, ffi::AV_NOPTS_VALUE, ffi::AV_NOPTS_VALUE)
+            .map(|(_, packet)| packet)
+    }
+
+    fn parse_raw(&mut self, codec: &AvCodecContext, data: *const u8, size: c_int, pts: i64, dts: i64)
+                 -> Result<(usize, Option<ParsedPacket>),c_int> {
+        let mut out_data = ptr::null_mut();
+        let mut out_size = 0;
+        let result = unsafe {
+            ffi::av_parser_parse2(self.context,
+                                  codec.context.ptr(),
+                                  &mut out_data,
+                                  &mut out_size,
+                                  data,
+                                  size,
+                                  pts,
+                                  dts,
+                                  -1)
+        };
+        if result < 0 {
+            return Err(result)
+        }
+        if out_size <= 0 {
+            return Ok((result as usize, None))
+        }
+
+        // The output points into either `data` or the parser's own buffer, so copy it out along
+        // with the padding decoders need.
+        let mut packet_data = Vec::with_capacity(out_size as usize + ffi::FF_INPUT_BUFFER_PADDING_SIZE);
+        unsafe {
+            packet_data.push_all(slice::from_raw_parts(out_data as *const u8, out_size as usize));
+        }
+        for _ in 0..ffi::FF_INPUT_BUFFER_PADDING_SIZE {
+            packet_data.push(0)
+        }
+
+        let context = unsafe {
+            &*self.context
+        };
+        Ok((result as usize, Some(ParsedPacket {
+            data: packet_data,
+            pts: context.pts,
+            dts: context.dts,
+            duration: context.duration,
+            key_frame: context.key_frame == 1,
+        })))
+    }
+}
+
+/// A complete packet split out by `AvParser`.
+pub struct ParsedPacket {
+    /// Includes `FF_INPUT_BUFFER_PADDING_SIZE` bytes of padding at the end.
+    pub data: Vec<u8>,
+    pub pts: i64,
+    pub dts: i64,
+    pub duration: c_int,
+    pub key_frame: bool,
+}
+
+impl ParsedPacket {
+    /// The packet data without the padding.
+    pub fn data(&self) -> &[u8] {
+        &self.data[..self.data.len() - ffi::FF_INPUT_BUFFER_PADDING_SIZE]
+    }
+
+    /// Wraps the data in a packet carrying its timestamps, ready for `AvCodecContext::decode_*`.
+    pub fn packet<'a>(&'a mut self) -> AvPacket<'a> {
+        let (pts, dts, duration) = (self.pts, self.dts, self.duration);
+        let mut packet = AvPacket::new(&mut self.data[..]);
+        packet.set_pts(pts);
+        packet.set_dts(dts);
+        packet.set_duration(duration);
+        packet
+    }
+}
+
+pub enum SubtitleRect {
+    /// An indexed-colour image; `palette` holds `nb_colors` ARGB entries in native-endian `u32`s.
+    Bitmap {
+        x: c_int,
+        y: c_int,
+        width: c_int,
+        height: c_int,
+        linesize: c_int,
+        data: Vec<u8>,
+        palette: Vec<u32>,
+    },
+    Text(String),
+    /// A full ASS `Dialogue:` line, including its style and override tags.
+    Ass(String),
+}
+
+pub struct AvSubtitle {
+    pub subtitle: ffi::AVSubtitle,
+}
+
+impl Drop for AvSubtitle {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::avsubtitle_free(&mut self.subtitle)
+        }
+    }
+}
+
+impl AvSubtitle {
+    pub fn new() -> AvSubtitle {
+        AvSubtitle {
+            subtitle: unsafe { mem::zeroed() },
+        }
+    }
+
+    /// Builds a text subtitle shown from `start` to `end` (in milliseconds) for encoding. Lines
+    /// are separated by `\n`.
+    pub fn from_text(start: i64, end: i64, text: &str) -> Result<AvSubtitle,c_int> {
+        let text = text.replace("\r\n", "\n");
+        let dialogue = format!("Dialogue: 0,{},{},Default,,0,0,0,,{}",
+                               ass_timestamp(start),
+                               ass_timestamp(end),
+                               text.replace("\n", "\\N"));
+        AvSubtitle::from_ass(start, end, &text, &dialogue)
+    }
+
+    /// Builds a subtitle from a full ASS `Dialogue:` line, as parsed against the header given to
+    /// `AvCodecContext::set_subtitle_header`.
+    pub fn from_ass(start: i64, end: i64, text: &str, dialogue: &str) -> Result<AvSubtitle,c_int> {
+        assert!(end >= start);
+        let text = try!(CString::new(text).or_else(|_| Err(ERROR_CSTRING)));
+        let dialogue = try!(CString::new(dialogue).or_else(|_| Err(ERROR_CSTRING)));
+
+        let mut subtitle = AvSubtitle::new();
+        subtitle.subtitle.pts = start * 1000;
+        subtitle.subtitle.end_display_time = (end - start) as u32;
+        unsafe {
+            // Everything is allocated by FFmpeg so that `avsubtitle_free` can release it.
+            let rects = ffi::av_mallocz(mem::size_of::<*mut ffi::AVSubtitleRect>() as size_t) as *mut *mut ffi::AVSubtitleRect;
+            if rects.is_null() {
+                return Err(ffi::AVERROR_ENOMEM)
+            }
+            subtitle.subtitle.rects = rects;
+
+            let rect = ffi::av_mallocz(mem::size_of::<ffi::AVSubtitleRect>() as size_t) as *mut ffi::AVSubtitleRect;
+            if rect.is_null() {
+                return Err(ffi::AVERROR_ENOMEM)
+            }
+            *rects = rect;
+            subtitle.subtitle.num_rects = 1;
+
+            (*rect).type_ = ffi::SUBTITLE_ASS;
+            (*rect).text = ffi::av_strdup(text.as_ptr());
+            (*rect).ass = ffi::av_strdup(dialogue.as_ptr());
+            if (*rect).text.is_null() || (*rect).ass.is_null() {
+                return Err(ffi::AVERROR_ENOMEM)
+            }
+        }
+        Ok(subtitle)
+    }
+
+    /// In `AV_TIME_BASE` units, or `AV_NOPTS_VALUE` if the decoder was not given a packet time
+    /// base with `AvCodecContext::set_pkt_timebase`.
+    pub fn pts(&self) -> i64 {
+        self.subtitle.pts
+    }
+
+    /// In milliseconds, relative to `pts`.
+    pub fn start_display_time(&self) -> u32 {
+        self.subtitle.start_display_time
+    }
+
+    /// In milliseconds, relative to `pts`.
+    pub fn end_display_time(&self) -> u32 {
+        self.subtitle.end_display_time
+    }
+
+    pub fn rects(&self) -> Vec<SubtitleRect> {
+        let mut rects = Vec::new();
+        unsafe {
+            for i in 0..self.subtitle.num_rects {
+                let rect = *self.subtitle.rects.offset(i as isize);
+                if rect.is_null() {
+                    continue
+                }
+                let rect = &*rect;
+                match rect.type_ {
+                    ffi::SUBTITLE_BITMAP => {
+                        let linesize = rect.pict.linesize[0];
+                        let data = if rect.pict.data[0].is_null() {
+                            Vec::new()
+                        } else {
+                            slice::from_raw_parts(rect.pict.data[0] as *const u8, (linesize * rect.h) as usize).to_vec()
+                        };
+                        let palette = if rect.pict.data[1].is_null() {
+                            Vec::new()
+                        } else {
+                            slice::from_raw_parts(rect.pict.data[1] as *const u32, rect.nb_colors as usize).to_vec()
+                        };
+                        rects.push(SubtitleRect::Bitmap {
+                            x: rect.x,
+                            y: rect.y,
+                            width: rect.w,
+                            height: rect.h,
+                            linesize: linesize,
+                            data: data,
+                            palette: palette,
+                        });
+                    }
+                    ffi::SUBTITLE_TEXT => {
+                        rects.push(SubtitleRect::Text(from_cstr(rect.text).unwrap_or(String::new())));
+                    }
+                    ffi::SUBTITLE_ASS => {
+                        rects.push(SubtitleRect::Ass(from_cstr(rect.ass).unwrap_or(String::new())));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        rects
+    }
+}
+
+/// Formats milliseconds as an ASS `H:MM:SS.cc` timestamp.
+fn ass_timestamp(ms: i64) -> String {
+    let cs = ms / 10;
+    format!("{}:{:02}:{:02}.{:02}", cs / 360000, (cs / 6000) % 60, (cs / 100) % 60, cs % 100)
+}
+
+macro_rules! codec_ids {
+    ($($media_type:ident { $($variant:ident => $name:tt,)* })*) => (
+        /// A codec known to `libavcodec` 56 (FFmpeg 2.5), identified by its descriptor name.
+        ///
+        /// The numeric `AVCodecID` values are not stable across `libavcodec` versions, so they are
+        /// looked up by name at runtime; see `id` and `from_id`. Codecs added after `libavcodec`
+        /// 54 have no `id` when linked against it.
+        #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+        pub enum CodecId {
+            $($($variant,)*)*
+        }
+
+        impl CodecId {
+            pub fn name(&self) -> &'static str {
+                match *self {
+                    $($(CodecId::$variant => $name,)*)*
+                }
+            }
+
+            /// One of the `AVMEDIA_TYPE_*` constants.
+            pub fn media_type(&self) -> c_int {
+                match *self {
+                    $($(CodecId::$variant => ffi::$media_type,)*)*
+                }
+            }
+
+            pub fn from_name(name: &str) -> Option<CodecId> {
+                match name {
+                    $($($name => Some(CodecId::$variant),)*)*
+                    _ => None,
+                }
+            }
+        }
+    )
+}
+
+codec_ids! {
+    AVMEDIA_TYPE_VIDEO {
+        Mpeg1video => "mpeg1video",
+        Mpeg2video => "mpeg2video",
+        H261 => "h261",
+        H263 => "h263",
+        Rv10 => "rv10",
+        Rv20 => "rv20",
+        Mjpeg => "mjpeg",
+        Mjpegb => "mjpegb",
+        Ljpeg => "ljpeg",
+        Sp5x => "sp5x",
+        Jpegls => "jpegls",
+        Mpeg4 => "mpeg4",
+        Rawvideo => "rawvideo",
+        Msmpeg4v1 => "msmpeg4v1",
+        Msmpeg4v2 => "msmpeg4v2",
+        Msmpeg4v3 => "msmpeg4v3",
+        Wmv1 => "wmv1",
+        Wmv2 => "wmv2",
+        H263p => "h263p",
+        H263i => "h263i",
+        Flv1 => "flv1",
+        Svq1 => "svq1",
+        Svq3 => "svq3",
+        Dvvideo => "dvvideo",
+        Huffyuv => "huffyuv",
+        Cyuv => "cyuv",
+        H264 => "h264",
+        Indeo3 => "indeo3",
+        Vp3 => "vp3",
+        Theora => "theora",
+        Asv1 => "asv1",
+        Asv2 => "asv2",
+        Ffv1 => "ffv1",
+        FourXm => "4xm",
+        Vcr1 => "vcr1",
+        Cljr => "cljr",
+        Mdec => "mdec",
+        Roq => "roq",
+        Interplayvideo => "interplayvideo",
+        XanWc3 => "xan_wc3",
+        XanWc4 => "xan_wc4",
+        Rpza => "rpza",
+        Cinepak => "cinepak",
+        WsVqa => "ws_vqa",
+        Msrle => "msrle",
+        Msvideo1 => "msvideo1",
+        Idcin => "idcin",
+        EightBps => "8bps",
+        Smc => "smc",
+        Flic => "flic",
+        Truemotion1 => "truemotion1",
+        Vmdvideo => "vmdvideo",
+        Mszh => "mszh",
+        Zlib => "zlib",
+        Qtrle => "qtrle",
+        Tscc => "tscc",
+        Ulti => "ulti",
+        Qdraw => "qdraw",
+        Vixl => "vixl",
+        Qpeg => "qpeg",
+        Png => "png",
+        Ppm => "ppm",
+        Pbm => "pbm",
+        Pgm => "pgm",
+        Pgmyuv => "pgmyuv",
+        Pam => "pam",
+        Ffvhuff => "ffvhuff",
+        Rv30 => "rv30",
+        Rv40 => "rv40",
+        Vc1 => "vc1",
+        Wmv3 => "wmv3",
+        Loco => "loco",
+        Wnv1 => "wnv1",
+        Aasc => "aasc",
+        Indeo2 => "indeo2",
+        Fraps => "fraps",
+        Truemotion2 => "truemotion2",
+        Bmp => "bmp",
+        Cscd => "cscd",
+        Mmvideo => "mmvideo",
+        Zmbv => "zmbv",
+        Avs => "avs",
+        Smackvideo => "smackvideo",
+        Nuv => "nuv",
+        Kmvc => "kmvc",
+        Flashsv => "flashsv",
+        Cavs => "cavs",
+        Jpeg2000 => "jpeg2000",
+        Vmnc => "vmnc",
+        Vp5 => "vp5",
+        Vp6 => "vp6",
+        Vp6f => "vp6f",
+        Targa => "targa",
+        Dsicinvideo => "dsicinvideo",
+        Tiertexseqvideo => "tiertexseqvideo",
+        Tiff => "tiff",
+        Gif => "gif",
+        Dxa => "dxa",
+        Dnxhd => "dnxhd",
+        Thp => "thp",
+        Sgi => "sgi",
+        C93 => "c93",
+        Bethsoftvid => "bethsoftvid",
+        Ptx => "ptx",
+        Txd => "txd",
+        Vp6a => "vp6a",
+        Amv => "amv",
+        Vb => "vb",
+        Pcx => "pcx",
+        Sunrast => "sunrast",
+        Indeo4 => "indeo4",
+        Indeo5 => "indeo5",
+        Mimic => "mimic",
+        Rl2 => "rl2",
+        Escape124 => "escape124",
+        Dirac => "dirac",
+        Bfi => "bfi",
+        Cmv => "cmv",
+        Motionpixels => "motionpixels",
+        Tgv => "tgv",
+        Tgq => "tgq",
+        Tqi => "tqi",
+        Aura => "aura",
+        Aura2 => "aura2",
+        V210x => "v210x",
+        Tmv => "tmv",
+        V210 => "v210",
+        Dpx => "dpx",
+        Mad => "mad",
+        Frwu => "frwu",
+        Flashsv2 => "flashsv2",
+        Cdgraphics => "cdgraphics",
+        R210 => "r210",
+        Anm => "anm",
+        Binkvideo => "binkvideo",
+        IffIlbm => "iff_ilbm",
+        Kgv1 => "kgv1",
+        Yop => "yop",
+        Vp8 => "vp8",
+        Pictor => "pictor",
+        Ansi => "ansi",
+        A64Multi => "a64_multi",
+        A64Multi5 => "a64_multi5",
+        R10k => "r10k",
+        Mxpeg => "mxpeg",
+        Lagarith => "lagarith",
+        Prores => "prores",
+        Jv => "jv",
+        Dfa => "dfa",
+        Wmv3image => "wmv3image",
+        Vc1image => "vc1image",
+        Utvideo => "utvideo",
+        BmvVideo => "bmv_video",
+        Vble => "vble",
+        Dxtory => "dxtory",
+        V410 => "v410",
+        Xwd => "xwd",
+        Cdxl => "cdxl",
+        Xbm => "xbm",
+        Zerocodec => "zerocodec",
+        Mss1 => "mss1",
+        Msa1 => "msa1",
+        Tscc2 => "tscc2",
+        Mts2 => "mts2",
+        Cllc => "cllc",
+        Mss2 => "mss2",
+        Vp9 => "vp9",
+        Aic => "aic",
+        Escape130 => "escape130",
+        G2m => "g2m",
+        Webp => "webp",
+        Hnm4video => "hnm4video",
+        Hevc => "hevc",
+        Fic => "fic",
+        AliasPix => "alias_pix",
+        BrenderPix => "brender_pix",
+        PafVideo => "paf_video",
+        Exr => "exr",
+        Vp7 => "vp7",
+        Sanm => "sanm",
+        Sgirle => "sgirle",
+        Mvc1 => "mvc1",
+        Mvc2 => "mvc2",
+        Y41p => "y41p",
+        Avrp => "avrp",
+        Zero12v => "012v",
+        Avui => "avui",
+        Ayuv => "ayuv",
+        TargaY216 => "targa_y216",
+        V308 => "v308",
+        V408 => "v408",
+        Yuv4 => "yuv4",
+        Avrn => "avrn",
+        Cpia => "cpia",
+        Xface => "xface",
+        Snow => "snow",
+        Smvjpeg => "smvjpeg",
+    }
+    AVMEDIA_TYPE_AUDIO {
+        PcmS16le => "pcm_s16le",
+        PcmS16be => "pcm_s16be",
+        PcmU16le => "pcm_u16le",
+        PcmU16be => "pcm_u16be",
+        PcmS8 => "pcm_s8",
+        PcmU8 => "pcm_u8",
+        PcmMulaw => "pcm_mulaw",
+        PcmAlaw => "pcm_alaw",
+        PcmS32le => "pcm_s32le",
+        PcmS32be => "pcm_s32be",
+        PcmU32le => "pcm_u32le",
+        PcmU32be => "pcm_u32be",
+        PcmS24le => "pcm_s24le",
+        PcmS24be => "pcm_s24be",
+        PcmU24le => "pcm_u24le",
+        PcmU24be => "pcm_u24be",
+        PcmS24daud => "pcm_s24daud",
+        PcmZork => "pcm_zork",
+        PcmS16lePlanar => "pcm_s16le_planar",
+        PcmDvd => "pcm_dvd",
+        PcmF32be => "pcm_f32be",
+        PcmF32le => "pcm_f32le",
+        PcmF64be => "pcm_f64be",
+        PcmF64le => "pcm_f64le",
+        PcmBluray => "pcm_bluray",
+        PcmLxf => "pcm_lxf",
+        S302m => "s302m",
+        PcmS8Planar => "pcm_s8_planar",
+        PcmS24lePlanar => "pcm_s24le_planar",
+        PcmS32lePlanar => "pcm_s32le_planar",
+        PcmS16bePlanar => "pcm_s16be_planar",
+        AdpcmImaQt => "adpcm_ima_qt",
+        AdpcmImaWav => "adpcm_ima_wav",
+        AdpcmImaDk3 => "adpcm_ima_dk3",
+        AdpcmImaDk4 => "adpcm_ima_dk4",
+        AdpcmImaWs => "adpcm_ima_ws",
+        AdpcmImaSmjpeg => "adpcm_ima_smjpeg",
+        AdpcmMs => "adpcm_ms",
+        Adpcm4xm => "adpcm_4xm",
+        AdpcmXa => "adpcm_xa",
+        AdpcmAdx => "adpcm_adx",
+        AdpcmEa => "adpcm_ea",
+        AdpcmG726 => "adpcm_g726",
+        AdpcmCt => "adpcm_ct",
+        AdpcmSwf => "adpcm_swf",
+        AdpcmYamaha => "adpcm_yamaha",
+        AdpcmSbpro4 => "adpcm_sbpro_4",
+        AdpcmSbpro3 => "adpcm_sbpro_3",
+        AdpcmSbpro2 => "adpcm_sbpro_2",
+        AdpcmThp => "adpcm_thp",
+        AdpcmImaAmv => "adpcm_ima_amv",
+        AdpcmEaR1 => "adpcm_ea_r1",
+        AdpcmEaR3 => "adpcm_ea_r3",
+        AdpcmEaR2 => "adpcm_ea_r2",
+        AdpcmImaEaSead => "adpcm_ima_ea_sead",
+        AdpcmImaEaEacs => "adpcm_ima_ea_eacs",
+        AdpcmEaXas => "adpcm_ea_xas",
+        AdpcmEaMaxisXa => "adpcm_ea_maxis_xa",
+        AdpcmImaIss => "adpcm_ima_iss",
+        AdpcmG722 => "adpcm_g722",
+        AdpcmImaApc => "adpcm_ima_apc",
+        AdpcmVima => "adpcm_vima",
+        AdpcmAfc => "adpcm_afc",
+        AdpcmImaOki => "adpcm_ima_oki",
+        AdpcmDtk => "adpcm_dtk",
+        AdpcmImaRad => "adpcm_ima_rad",
+        AdpcmG726le => "adpcm_g726le",
+        AmrNb => "amr_nb",
+        AmrWb => "amr_wb",
+        Ra144 => "ra_144",
+        Ra288 => "ra_288",
+        RoqDpcm => "roq_dpcm",
+        InterplayDpcm => "interplay_dpcm",
+        XanDpcm => "xan_dpcm",
+        SolDpcm => "sol_dpcm",
+        Mp2 => "mp2",
+        Mp3 => "mp3",
+        Aac => "aac",
+        Ac3 => "ac3",
+        Dts => "dts",
+        Vorbis => "vorbis",
+        Dvaudio => "dvaudio",
+        Wmav1 => "wmav1",
+        Wmav2 => "wmav2",
+        Mace3 => "mace3",
+        Mace6 => "mace6",
+        Vmdaudio => "vmdaudio",
+        Flac => "flac",
+        Mp3adu => "mp3adu",
+        Mp3on4 => "mp3on4",
+        Shorten => "shorten",
+        Alac => "alac",
+        WestwoodSnd1 => "westwood_snd1",
+        Gsm => "gsm",
+        Qdm2 => "qdm2",
+        Cook => "cook",
+        Truespeech => "truespeech",
+        Tta => "tta",
+        Smackaudio => "smackaudio",
+        Qcelp => "qcelp",
+        Wavpack => "wavpack",
+        Dsicinaudio => "dsicinaudio",
+        Imc => "imc",
+        Musepack7 => "musepack7",
+        Mlp => "mlp",
+        GsmMs => "gsm_ms",
+        Atrac3 => "atrac3",
+        Ape => "ape",
+        Nellymoser => "nellymoser",
+        Musepack8 => "musepack8",
+        Speex => "speex",
+        Wmavoice => "wmavoice",
+        Wmapro => "wmapro",
+        Wmalossless => "wmalossless",
+        Atrac3p => "atrac3p",
+        Eac3 => "eac3",
+        Sipr => "sipr",
+        Mp1 => "mp1",
+        Twinvq => "twinvq",
+        Truehd => "truehd",
+        Mp4als => "mp4als",
+        Atrac1 => "atrac1",
+        BinkaudioRdft => "binkaudio_rdft",
+        BinkaudioDct => "binkaudio_dct",
+        AacLatm => "aac_latm",
+        Qdmc => "qdmc",
+        Celt => "celt",
+        G7231 => "g723_1",
+        G729 => "g729",
+        EightSvxExp => "8svx_exp",
+        EightSvxFib => "8svx_fib",
+        BmvAudio => "bmv_audio",
+        Ralf => "ralf",
+        Iac => "iac",
+        Ilbc => "ilbc",
+        Opus => "opus",
+        Comfortnoise => "comfortnoise",
+        Tak => "tak",
+        Metasound => "metasound",
+        PafAudio => "paf_audio",
+        On2avc => "on2avc",
+        Ffwavesynth => "ffwavesynth",
+        Sonic => "sonic",
+        Sonicls => "sonicls",
+        Evrc => "evrc",
+        Smv => "smv",
+    }
+    AVMEDIA_TYPE_SUBTITLE {
+        DvdSubtitle => "dvd_subtitle",
+        DvbSubtitle => "dvb_subtitle",
+        Text => "text",
+        Xsub => "xsub",
+        Ssa => "ssa",
+        MovText => "mov_text",
+        HdmvPgsSubtitle => "hdmv_pgs_subtitle",
+        DvbTeletext => "dvb_teletext",
+        Srt => "srt",
+        Microdvd => "microdvd",
+        Eia608 => "eia_608",
+        Jacosub => "jacosub",
+        Sami => "sami",
+        Realtext => "realtext",
+        Stl => "stl",
+        Subviewer1 => "subviewer1",
+        Subviewer => "subviewer",
+        Subrip => "subrip",
+        Webvtt => "webvtt",
+        Mpl2 => "mpl2",
+        Vplayer => "vplayer",
+        Pjs => "pjs",
+        Ass => "ass",
+    }
+    AVMEDIA_TYPE_DATA {
+        Ttf => "ttf",
+        Bintext => "bintext",
+        Xbin => "xbin",
+        Idf => "idf",
+        Otf => "otf",
+        SmpteKlv => "smpte_klv",
+        DvdNavPacket => "dvd_nav_packet",
+        TimedId3 => "timed_id3",
+        BinData => "bin_data",
+    }
+}
+
+impl CodecId {
+    /// Returns `None` if the linked `libavcodec` predates this codec.
+    pub fn id(&self) -> Option<AvCodecId> {
+        let name = CString::new(self.name()).unwrap();
+        unsafe {
+            let descriptor = ffi::avcodec_descriptor_get_by_name(name.as_ptr());
+            if descriptor.is_null() {
+                None
+            } else {
+                Some((*descriptor).id)
+            }
+        }
+    }
+
+    pub fn from_id(id: AvCodecId) -> Option<CodecId> {
+        unsafe {
+            let descriptor = ffi::avcodec_descriptor_get(id);
+            if descriptor.is_null() {
+                return None
+            }
+            from_cstr((*descriptor).name).and_then(|name| CodecId::from_name(&name))
+        }
+    }
+
+    pub fn long_name(&self) -> Option<String> {
+        let name = CString::new(self.name()).unwrap();
+        unsafe {
+            let descriptor = ffi::avcodec_descriptor_get_by_name(name.as_ptr());
+            if descriptor.is_null() {
+                None
+            } else {
+                from_cstr((*descriptor).long_name)
+            }
+        }
+    }
+
+    pub fn find_decoder(&self) -> Result<AvCodec,()> {
+        self.id().ok_or(()).and_then(AvCodec::find_decoder)
+    }
+
+    pub fn find_encoder(&self) -> Result<AvCodec,()> {
+        self.id().ok_or(()).and_then(AvCodec::find_encoder)
+    }
 }
@@ -1,12 +1,23 @@
 use libc::{ c_int, c_uint, c_void, size_t };
+use libc::{ EACCES, EAGAIN, ECONNABORTED, ECONNREFUSED, ECONNRESET, EEXIST, EINVAL, EIO, ENOENT, ENOSYS, ENOTCONN, EPIPE, ETIMEDOUT };
 use std::slice::{ from_raw_parts_mut, from_raw_parts };
-use std::ptr::null_mut;
-use std::mem::forget;
+use std::cmp::min;
+use std::i32;
+use std::ops::Deref;
+use std::ptr::{ self, null, null_mut };
+use std::marker::PhantomData;
+use std::mem::{ forget, size_of };
 use std::ffi::CString;
-use std::old_io::Reader;
+use std::old_io::{ File, Reader, Writer, Seek, SeekStyle, IoResult, IoError };
+use std::old_path::Path;
+use std::old_io::{ EndOfFile, FileNotFound, PathDoesntExist, PermissionDenied, ConnectionRefused, ConnectionReset,
+                   ConnectionAborted, NotConnected, BrokenPipe, Closed, PathAlreadyExists, ResourceUnavailable,
+                   InvalidInput, TimedOut, IoUnavailable };
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
 
-use avutil::{ AvDictionary };
-use avcodec::{ AvPacket, AvCodec, AvCodecContext };
+use avutil::{ AvDictionary, AvFrame, Options, SideData, from_cstr };
+use avcodec::{ AvBitstreamFilter, AvPacket, AvCodec, AvCodecContext, AvCodecId, AvSubtitle, CodecId, DecoderOptions, DEFAULT_ASS_HEADER, ERROR_CSTRING };
 
 use ffi;
 
@@ -16,28 +27,389 @@ pub fn init() {
     }
 }
 
+/// Sets up the network protocols (`tcp:`, `http:`, ...), which `init` does not.
+pub fn network_init() {
+    unsafe {
+        ffi::avformat_network_init();
+    }
+}
+
+/// Restricts which protocols an input or `Avio` may open, e.g. `&["file"]` to stop a playlist
+/// from referring to network resources. Applies to everything opened through these options,
+/// including nested resources such as HLS segments.
+///
+/// FFmpeg only has this option from 3.0 on. Older versions hand it back among the options not
+/// found, and enforce nothing, so check for that with `unapplied_restriction`.
+pub fn set_protocol_whitelist(options: &mut AvDictionary, protocols: &[&str]) {
+    options.set("protocol_whitelist", &protocols.connect(","));
+}
+
+/// The opposite of `set_protocol_whitelist`, with the same caveat.
+pub fn set_protocol_blacklist(options: &mut AvDictionary, protocols: &[&str]) {
+    options.set("protocol_blacklist", &protocols.connect(","));
+}
+
+const RESTRICTION_OPTIONS: [&'static str; 4] = [
+    "protocol_whitelist", "protocol_blacklist", "format_whitelist", "codec_whitelist",
+];
+
+/// Returns the first whitelist or blacklist option left among the options not found by an open,
+/// meaning that this version of FFmpeg doesn't support it and the restriction wasn't applied.
+pub fn unapplied_restriction(options_not_found: &AvDictionary) -> Option<&'static str> {
+    RESTRICTION_OPTIONS.iter().map(|key| *key).find(|key| options_not_found.get(key).is_some())
+}
+
 pub fn version() -> c_uint {
     unsafe {
         ffi::avformat_version()
     }
 }
 
+fn split_list(list: Option<String>) -> Vec<String> {
+    list.map(|list| {
+        list.split(',').map(|v| v.trim()).filter(|v| !v.is_empty()).map(|v| v.to_string()).collect()
+    }).unwrap_or(Vec::new())
+}
+
+#[allow(missing_copy_implementations)]
+pub struct AvInputFormat {
+    pub format: *mut ffi::AVInputFormat,
+}
+
+impl AvInputFormat {
+    pub fn find(name: &str) -> Option<AvInputFormat> {
+        let name = match CString::new(name) {
+            Ok(name) => name,
+            Err(_) => return None,
+        };
+        let format = unsafe {
+            ffi::av_find_input_format(name.as_ptr())
+        };
+        if format.is_null() {
+            None
+        } else {
+            Some(AvInputFormat {
+                format: format,
+            })
+        }
+    }
+
+    /// Guesses the format of the start of a stream, returning it along with its score (out of
+    /// `AVPROBE_SCORE_MAX`). The filename, if any, is only used as an extension hint.
+    pub fn probe(data: &[u8], filename: Option<&str>) -> Option<(AvInputFormat, c_int)> {
+        // The probe buffer must be followed by zeroed padding.
+        let mut buf = Vec::with_capacity(data.len() + ffi::AVPROBE_PADDING_SIZE);
+        buf.push_all(data);
+        for _ in 0..ffi::AVPROBE_PADDING_SIZE {
+            buf.push(0);
+        }
+
+        let filename = match CString::new(filename.unwrap_or("")) {
+            Ok(filename) => filename,
+            Err(_) => return None,
+        };
+        let mut probe_data = ffi::AVProbeData {
+            filename: filename.as_ptr(),
+            buf: buf.as_mut_ptr(),
+            buf_size: data.len() as c_int,
+            mime_type: null(),
+        };
+        let mut score = 0;
+        let format = unsafe {
+            ffi::av_probe_input_format3(&mut probe_data, 1, &mut score)
+        };
+        if format.is_null() {
+            None
+        } else {
+            Some((AvInputFormat {
+                format: format,
+            }, score))
+        }
+    }
+
+    /// Probes the format by reading up to `max_probe_size` bytes (0 for FFmpeg's default) from
+    /// `avio`, which is rewound afterwards so that it can still be opened with
+    /// `OpenOptions::format`.
+    pub fn probe_avio<T>(avio: &AvioContext<T>, max_probe_size: usize) -> Result<(AvInputFormat, c_int), c_int> {
+        AvInputFormat::probe_raw(avio.context, max_probe_size)
+    }
+
+    fn probe_raw(pb: *mut ffi::AVIOContext, max_probe_size: usize) -> Result<(AvInputFormat, c_int), c_int> {
+        let mut format = null_mut();
+        let result = unsafe {
+            ffi::av_probe_input_buffer2(pb, &mut format, null(), null_mut(), 0, max_probe_size as c_uint)
+        };
+        if result >= 0 && !format.is_null() {
+            Ok((AvInputFormat {
+                format: format,
+            }, result))
+        } else {
+            Err(result)
+        }
+    }
+
+    pub fn name(&self) -> String {
+        unsafe {
+            from_cstr((*self.format).name).unwrap_or(String::new())
+        }
+    }
+
+    pub fn long_name(&self) -> Option<String> {
+        unsafe {
+            from_cstr((*self.format).long_name)
+        }
+    }
+
+    /// File extensions used only as a fallback hint when probing the content is inconclusive.
+    pub fn extensions(&self) -> Vec<String> {
+        unsafe {
+            split_list(from_cstr((*self.format).extensions))
+        }
+    }
+
+    pub fn mime_types(&self) -> Vec<String> {
+        unsafe {
+            split_list(from_cstr((*self.format).mime_type))
+        }
+    }
+
+    /// A combination of the `AVFMT_*` flags.
+    pub fn flags(&self) -> c_int {
+        unsafe {
+            (*self.format).flags
+        }
+    }
+}
+
+#[allow(missing_copy_implementations)]
+pub struct AvOutputFormat {
+    pub format: *mut ffi::AVOutputFormat,
+}
+
+impl AvOutputFormat {
+    pub fn name(&self) -> String {
+        unsafe {
+            from_cstr((*self.format).name).unwrap_or(String::new())
+        }
+    }
+
+    pub fn long_name(&self) -> Option<String> {
+        unsafe {
+            from_cstr((*self.format).long_name)
+        }
+    }
+
+    pub fn extensions(&self) -> Vec<String> {
+        unsafe {
+            split_list(from_cstr((*self.format).extensions))
+        }
+    }
+
+    pub fn mime_types(&self) -> Vec<String> {
+        unsafe {
+            split_list(from_cstr((*self.format).mime_type))
+        }
+    }
+
+    /// A combination of the `AVFMT_*` flags.
+    pub fn flags(&self) -> c_int {
+        unsafe {
+            (*self.format).flags
+        }
+    }
+
+    pub fn default_audio_codec(&self) -> AvCodecId {
+        unsafe {
+            (*self.format).audio_codec
+        }
+    }
+
+    pub fn default_video_codec(&self) -> AvCodecId {
+        unsafe {
+            (*self.format).video_codec
+        }
+    }
+
+    pub fn default_subtitle_codec(&self) -> AvCodecId {
+        unsafe {
+            (*self.format).subtitle_codec
+        }
+    }
+}
+
+/// Iterates over every registered demuxer. Call `init` first.
+pub fn demuxers() -> Demuxers {
+    Demuxers {
+        prev: null_mut(),
+    }
+}
+
+pub struct Demuxers {
+    prev: *mut ffi::AVInputFormat,
+}
+
+impl Iterator for Demuxers {
+    type Item = AvInputFormat;
+
+    fn next(&mut self) -> Option<AvInputFormat> {
+        unsafe {
+            self.prev = ffi::av_iformat_next(self.prev);
+        }
+        if self.prev.is_null() {
+            None
+        } else {
+            Some(AvInputFormat {
+                format: self.prev,
+            })
+        }
+    }
+}
+
+/// Iterates over every registered muxer. Call `init` first.
+pub fn muxers() -> Muxers {
+    Muxers {
+        prev: null_mut(),
+    }
+}
+
+pub struct Muxers {
+    prev: *mut ffi::AVOutputFormat,
+}
+
+impl Iterator for Muxers {
+    type Item = AvOutputFormat;
+
+    fn next(&mut self) -> Option<AvOutputFormat> {
+        unsafe {
+            self.prev = ffi::av_oformat_next(self.prev);
+        }
+        if self.prev.is_null() {
+            None
+        } else {
+            Some(AvOutputFormat {
+                format: self.prev,
+            })
+        }
+    }
+}
+
+/// The I/O behind a custom `AvioContext`.
+///
+/// Errors are reported to FFmpeg as the matching `AVERROR` code (`EndOfFile` as `AVERROR_EOF`).
+/// The last one is kept for `AvioContext::take_error` until a read succeeds, except for
+/// `EndOfFile` and the `IoUnavailable` of operations left unimplemented, which FFmpeg probes for
+/// routinely (e.g. the size of a stream that can't seek).
 pub trait AvioContextCallbacks {
-    fn read(&mut self, _data: &mut [u8]) -> Result<usize, ()> { Err(()) }
-    fn write(&mut self, _data: &[u8]) -> Result<usize, ()> { Err(()) }
-    fn seek(&mut self, _offset: i64, _whence: i32) -> Result<u64, ()> { Err(()) }
+    fn read(&mut self, _data: &mut [u8]) -> IoResult<usize> { Err(unsupported_io()) }
+    fn write(&mut self, _data: &[u8]) -> IoResult<usize> { Err(unsupported_io()) }
+    /// `whence` is one of `SEEK_SET`, `SEEK_CUR` or `SEEK_END`, possibly with `AVSEEK_FORCE`,
+    /// or `AVSEEK_SIZE` to return the stream size without seeking.
+    fn seek(&mut self, _offset: i64, _whence: i32) -> IoResult<u64> { Err(unsupported_io()) }
+}
+
+fn unsupported_io() -> IoError {
+    IoError {
+        kind: IoUnavailable,
+        desc: "operation not supported",
+        detail: None,
+    }
+}
+
+fn invalid_seek() -> IoError {
+    IoError {
+        kind: InvalidInput,
+        desc: "invalid seek",
+        detail: None,
+    }
+}
+
+/// Maps an I/O error onto the `AVERROR` code FFmpeg would use for it.
+pub fn io_error_to_averror(error: &IoError) -> c_int {
+    let errno = match error.kind {
+        EndOfFile => return ffi::AVERROR_EOF,
+        FileNotFound | PathDoesntExist => ENOENT,
+        PermissionDenied => EACCES,
+        ConnectionRefused => ECONNREFUSED,
+        ConnectionReset => ECONNRESET,
+        ConnectionAborted => ECONNABORTED,
+        NotConnected => ENOTCONN,
+        BrokenPipe | Closed => EPIPE,
+        PathAlreadyExists => EEXIST,
+        ResourceUnavailable => EAGAIN,
+        InvalidInput => EINVAL,
+        TimedOut => ETIMEDOUT,
+        IoUnavailable => ENOSYS,
+        _ => EIO,
+    };
+    -errno
 }
 
 pub struct AvioContext<C> {
     pub context: *mut ffi::AVIOContext,
-    buffer: *mut c_void,
     callbacks: C,
+    error: Option<IoError>,
+}
+
+// `opaque` points at the boxed context itself, which does not move with the box.
+unsafe impl<C: Send> Send for AvioContext<C> {}
+
+#[unsafe_destructor]
+impl<C> Drop for AvioContext<C> {
+    fn drop(&mut self) {
+        unsafe {
+            if !self.context.is_null() && (*self.context).write_flag != 0 {
+                ffi::avio_flush(self.context);
+            }
+            self.free();
+        }
+    }
 }
 
-fn avio_transform_result<T, R, F: Fn(T) -> R>(r: Result<T, ()>, default: R, f: F) -> R {
-    match r {
-        Ok(v) => f(v),
-        _ => default,
+impl<C> AvioContext<C> {
+    unsafe fn free(&mut self) {
+        if !self.context.is_null() {
+            // FFmpeg may have replaced the buffer we allocated with one of a different size.
+            ffi::av_free((*self.context).buffer as *mut c_void);
+            ffi::av_free(self.context as *mut c_void);
+            self.context = null_mut();
+        }
+    }
+
+    /// Returns the last error the callbacks reported (other than end of file), which FFmpeg only
+    /// passes on as an `AVERROR` code.
+    pub fn take_error(&mut self) -> Option<IoError> {
+        self.error.take()
+    }
+
+    /// Tells FFmpeg whether the callbacks can seek. Muxers that would otherwise go back to patch
+    /// headers then either avoid it or fail, rather than calling a failing `seek`.
+    pub fn set_seekable(&self, seekable: bool) {
+        unsafe {
+            (*self.context).seekable = if seekable { 1 } else { 0 };
+        }
+    }
+
+    /// Writes out anything still buffered for writing.
+    pub fn flush(&self) {
+        unsafe {
+            ffi::avio_flush(self.context)
+        }
+    }
+
+    /// Flushes anything still buffered for writing and returns the callbacks, e.g. to retrieve
+    /// what was written to an `AvioMemoryWriter`.
+    ///
+    /// NB: Any `AvFormatContext` using this context must be gone, see `AvFormatContext::into_io`.
+    pub fn into_inner(self: Box<Self>) -> C {
+        unsafe {
+            // This writes through `opaque`, so it must happen before the context leaves the box.
+            ffi::avio_flush(self.context);
+            let mut self_ = *self;
+            self_.free();
+            let callbacks = ptr::read(&self_.callbacks);
+            forget(self_);
+            callbacks
+        }
     }
 }
 
@@ -46,95 +418,1430 @@ impl<C: AvioContextCallbacks> AvioContext<C> {
         &mut *(userdata as *mut Self)
     }
 
-    extern "C" fn cb_read(userdata: *mut c_void, data: *mut u8, size: c_int) -> c_int {
+    fn transform_error(&mut self, err: IoError) -> c_int {
+        let code = io_error_to_averror(&err);
+        if err.kind != EndOfFile && err.kind != IoUnavailable {
+            self.error = Some(err);
+        }
+        code
+    }
+
+    extern "C" fn cb_read(userdata: *mut c_void, data: *mut u8, size: c_int) -> c_int {
+        unsafe {
+            let self_ = AvioContext::<C>::cb_userdata(userdata);
+            match self_.callbacks.read(from_raw_parts_mut(data, size as usize)) {
+                // A read of nothing would otherwise be retried forever.
+                Ok(0) if size > 0 => ffi::AVERROR_EOF,
+                Ok(v) => {
+                    // Anything before this is no longer the cause of a later failure.
+                    self_.error = None;
+                    v as c_int
+                }
+                Err(err) => self_.transform_error(err),
+            }
+        }
+    }
+
+    extern "C" fn cb_write(userdata: *mut c_void, data: *mut u8, size: c_int) -> c_int {
+        unsafe {
+            let self_ = AvioContext::<C>::cb_userdata(userdata);
+            match self_.callbacks.write(from_raw_parts(data, size as usize)) {
+                Ok(v) => v as c_int,
+                Err(err) => self_.transform_error(err),
+            }
+        }
+    }
+
+    extern "C" fn cb_seek(userdata: *mut c_void, position: i64, whence: c_int) -> i64 {
+        unsafe {
+            let self_ = AvioContext::<C>::cb_userdata(userdata);
+            match self_.callbacks.seek(position, whence) {
+                Ok(v) => v as i64,
+                Err(err) => self_.transform_error(err) as i64,
+            }
+        }
+    }
+
+    pub fn new(buffer_size: usize, writable: bool, callbacks: C) -> Box<Self> {
+        unsafe {
+            let mut self_ = Box::new(AvioContext {
+                context: null_mut(),
+                callbacks: callbacks,
+                error: None,
+            });
+
+            let buffer = ffi::av_malloc(buffer_size as size_t);
+            assert!(!buffer.is_null());
+            self_.context = ffi::avio_alloc_context(buffer as *mut _,
+                                                    buffer_size as c_int,
+                                                    if writable { 1 } else { 0 },
+                                                    &mut *self_ as *mut _ as *mut c_void,
+                                                    Some(AvioContext::<C>::cb_read),
+                                                    Some(AvioContext::<C>::cb_write),
+                                                    Some(AvioContext::<C>::cb_seek));
+            if self_.context.is_null() {
+                ffi::av_free(buffer);
+            }
+            assert!(!self_.context.is_null());
+
+            self_
+        }
+    }
+}
+
+pub struct AvioContextReader<R> {
+    reader: R,
+    cancel: Option<CancelToken>,
+}
+
+impl<R> AvioContextReader<R> {
+    pub fn new(reader: R) -> Self {
+        AvioContextReader {
+            reader: reader,
+            cancel: None,
+        }
+    }
+
+    /// Fails every read once `cancel` is cancelled. A read already blocked in `reader` can't be
+    /// interrupted, and still has to return first.
+    pub fn with_cancel(reader: R, cancel: CancelToken) -> Self {
+        AvioContextReader {
+            reader: reader,
+            cancel: Some(cancel),
+        }
+    }
+
+    fn check_cancelled(&self) -> IoResult<()> {
+        match self.cancel {
+            Some(ref cancel) if cancel.is_cancelled() => Err(IoError {
+                kind: ConnectionAborted,
+                desc: "cancelled",
+                detail: None,
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<R: Reader> AvioContextCallbacks for AvioContextReader<R> {
+    fn read(&mut self, data: &mut [u8]) -> IoResult<usize> {
+        try!(self.check_cancelled());
+        self.reader.read(data)
+    }
+}
+
+/// An `AvioContextReader` for input that can also seek, which some formats need, e.g. MP4 files
+/// with their index at the end.
+pub struct AvioContextSeekableReader<R> {
+    inner: AvioContextReader<R>,
+}
+
+impl<R> AvioContextSeekableReader<R> {
+    pub fn new(reader: R) -> Self {
+        AvioContextSeekableReader {
+            inner: AvioContextReader::new(reader),
+        }
+    }
+
+    pub fn with_cancel(reader: R, cancel: CancelToken) -> Self {
+        AvioContextSeekableReader {
+            inner: AvioContextReader::with_cancel(reader, cancel),
+        }
+    }
+}
+
+impl<R: Reader + Seek> AvioContextCallbacks for AvioContextSeekableReader<R> {
+    fn read(&mut self, data: &mut [u8]) -> IoResult<usize> {
+        self.inner.read(data)
+    }
+
+    fn seek(&mut self, offset: i64, whence: i32) -> IoResult<u64> {
+        try!(self.inner.check_cancelled());
+        seek_stream(&mut self.inner.reader, offset, whence)
+    }
+}
+
+/// Carries out an `AvioContextCallbacks::seek` on a `Seek` implementation.
+fn seek_stream<S: Seek>(stream: &mut S, offset: i64, whence: i32) -> IoResult<u64> {
+    if whence & ffi::AVSEEK_SIZE != 0 {
+        let position = try!(stream.tell());
+        try!(stream.seek(0, SeekStyle::SeekEnd));
+        let size = try!(stream.tell());
+        try!(stream.seek(position as i64, SeekStyle::SeekSet));
+        return Ok(size)
+    }
+    let style = match whence & !ffi::AVSEEK_FORCE {
+        ffi::SEEK_SET => SeekStyle::SeekSet,
+        ffi::SEEK_CUR => SeekStyle::SeekCur,
+        ffi::SEEK_END => SeekStyle::SeekEnd,
+        _ => return Err(invalid_seek()),
+    };
+    try!(stream.seek(offset, style));
+    stream.tell()
+}
+
+impl<W: Writer + Seek> AvioContext<AvioContextWriter<W>> {
+    pub fn from_writer(writer: W) -> Box<Self> {
+        AvioContext::new(0x1000, true, AvioContextWriter::new(writer))
+    }
+}
+
+impl<W: Writer> AvioContext<AvioContextPipeWriter<W>> {
+    /// Writes to something that can't seek, such as a pipe or socket.
+    pub fn from_pipe(writer: W) -> Box<Self> {
+        let avio = AvioContext::new(0x1000, true, AvioContextPipeWriter::new(writer));
+        avio.set_seekable(false);
+        avio
+    }
+}
+
+pub struct AvioContextWriter<W> {
+    writer: W,
+}
+
+impl<W> AvioContextWriter<W> {
+    pub fn new(writer: W) -> Self {
+        AvioContextWriter {
+            writer: writer,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Writer + Seek> AvioContextCallbacks for AvioContextWriter<W> {
+    fn write(&mut self, data: &[u8]) -> IoResult<usize> {
+        try!(self.writer.write_all(data));
+        Ok(data.len())
+    }
+
+    fn seek(&mut self, offset: i64, whence: i32) -> IoResult<u64> {
+        seek_stream(&mut self.writer, offset, whence)
+    }
+}
+
+pub struct AvioContextPipeWriter<W> {
+    writer: W,
+}
+
+impl<W> AvioContextPipeWriter<W> {
+    pub fn new(writer: W) -> Self {
+        AvioContextPipeWriter {
+            writer: writer,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Writer> AvioContextCallbacks for AvioContextPipeWriter<W> {
+    fn write(&mut self, data: &[u8]) -> IoResult<usize> {
+        try!(self.writer.write_all(data));
+        Ok(data.len())
+    }
+}
+
+impl<D: Deref<Target=[u8]>> AvioContext<AvioMemoryReader<D>> {
+    /// Reads from a byte buffer, which may be borrowed (`&[u8]`) or owned (`Vec<u8>`).
+    pub fn from_bytes(data: D) -> Box<Self> {
+        AvioContext::new(0x1000, false, AvioMemoryReader::new(data))
+    }
+}
+
+impl AvioContext<AvioMemoryWriter> {
+    /// Writes into a growable buffer, which `into_inner` hands back once muxing is done. Seeking
+    /// is supported, so muxers that go back to rewrite headers (such as MP4) work.
+    pub fn memory_writer() -> Box<Self> {
+        AvioContext::new(0x1000, true, AvioMemoryWriter::new())
+    }
+}
+
+/// Works out where a seek callback should move to, or returns the stream size for `AVSEEK_SIZE`.
+fn avio_seek_target(position: usize, len: usize, offset: i64, whence: i32) -> IoResult<u64> {
+    if whence & ffi::AVSEEK_SIZE != 0 {
+        return Ok(len as u64)
+    }
+    let base = match whence & !ffi::AVSEEK_FORCE {
+        ffi::SEEK_SET => 0,
+        ffi::SEEK_CUR => position as i64,
+        ffi::SEEK_END => len as i64,
+        _ => return Err(invalid_seek()),
+    };
+    let target = base + offset;
+    if target < 0 {
+        Err(invalid_seek())
+    } else {
+        Ok(target as u64)
+    }
+}
+
+pub struct AvioMemoryReader<D> {
+    data: D,
+    position: usize,
+}
+
+impl<D: Deref<Target=[u8]>> AvioMemoryReader<D> {
+    pub fn new(data: D) -> Self {
+        AvioMemoryReader {
+            data: data,
+            position: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> D {
+        self.data
+    }
+}
+
+impl<D: Deref<Target=[u8]>> AvioContextCallbacks for AvioMemoryReader<D> {
+    fn read(&mut self, data: &mut [u8]) -> IoResult<usize> {
+        let position = min(self.position, self.data.len());
+        let len = data.clone_from_slice(&(*self.data)[position..]);
+        self.position = position + len;
+        Ok(len)
+    }
+
+    fn seek(&mut self, offset: i64, whence: i32) -> IoResult<u64> {
+        let target = try!(avio_seek_target(self.position, self.data.len(), offset, whence));
+        if whence & ffi::AVSEEK_SIZE == 0 {
+            self.position = target as usize;
+        }
+        Ok(target)
+    }
+}
+
+pub struct AvioMemoryWriter {
+    data: Vec<u8>,
+    position: usize,
+}
+
+impl AvioMemoryWriter {
+    pub fn new() -> Self {
+        AvioMemoryWriter {
+            data: Vec::new(),
+            position: 0,
+        }
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.data
+    }
+}
+
+impl AvioContextCallbacks for AvioMemoryWriter {
+    fn write(&mut self, data: &[u8]) -> IoResult<usize> {
+        // Seeking past the end leaves a gap, which is zeroed.
+        let end = self.position + data.len();
+        if end > self.data.len() {
+            self.data.resize(end, 0);
+        }
+        self.data[self.position..end].clone_from_slice(data);
+        self.position = end;
+        Ok(data.len())
+    }
+
+    fn seek(&mut self, offset: i64, whence: i32) -> IoResult<u64> {
+        let target = try!(avio_seek_target(self.position, self.data.len(), offset, whence));
+        if whence & ffi::AVSEEK_SIZE == 0 {
+            self.position = target as usize;
+        }
+        Ok(target)
+    }
+}
+
+/// A file or connection opened through FFmpeg's own protocols, such as `file:`, `pipe:`, `tcp:`,
+/// `udp:` or `http:`. Network protocols need `network_init` first.
+///
+/// It can be read and written directly, or handed to `AvFormatContext::open_input` and
+/// `open_output_avio`.
+pub struct Avio {
+    pub context: *mut ffi::AVIOContext,
+    interrupt: Option<Box<InterruptCallback>>,
+}
+
+unsafe impl Send for Avio {}
+
+impl Drop for Avio {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::avio_closep(&mut self.context);
+        }
+    }
+}
+
+impl Avio {
+    /// Opens `url` with `flags` from `AVIO_FLAG_*`. `options` are passed to the protocol, e.g.
+    /// `timeout` or `user_agent`; see also `set_protocol_whitelist` and `unapplied_restriction`.
+    pub fn open(url: &str, flags: c_int, options: AvDictionary) -> (Result<Avio, c_int>, AvDictionary) {
+        Avio::open_internal(url, flags, None, options)
+    }
+
+    pub fn open_interruptible(url: &str, flags: c_int, interrupt: InterruptCallback, options: AvDictionary)
+                              -> (Result<Avio, c_int>, AvDictionary) {
+        Avio::open_internal(url, flags, Some(interrupt), options)
+    }
+
+    fn open_internal(url: &str, flags: c_int, interrupt: Option<InterruptCallback>, options: AvDictionary)
+                     -> (Result<Avio, c_int>, AvDictionary) {
+        let url = match CString::new(url) {
+            Ok(url) => url,
+            Err(_) => return (Err(ERROR_CSTRING), options),
+        };
+        unsafe {
+            let mut interrupt = interrupt.map(Box::new);
+            let interrupt_cb = interrupt.as_mut().map(|interrupt| interrupt.as_raw());
+
+            let mut context = null_mut();
+            let mut options_not_found = options.dictionary;
+            let result = ffi::avio_open2(&mut context,
+                                         url.as_ptr(),
+                                         flags,
+                                         match interrupt_cb {
+                                             Some(ref interrupt_cb) => interrupt_cb as *const ffi::AVIOInterruptCB,
+                                             None => null(),
+                                         },
+                                         &mut options_not_found);
+            forget(options);
+            let options_not_found = AvDictionary {
+                dictionary: options_not_found,
+            };
+
+            if result >= 0 {
+                (Ok(Avio {
+                    context: context,
+                    interrupt: interrupt,
+                }), options_not_found)
+            } else {
+                (Err(result), options_not_found)
+            }
+        }
+    }
+
+    /// Returns 0 at the end of the stream.
+    pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, c_int> {
+        assert!(buf.len() <= (i32::MAX as usize));
+        let result = unsafe {
+            ffi::avio_read(self.context, buf.as_mut_ptr(), buf.len() as c_int)
+        };
+        if result >= 0 {
+            Ok(result as usize)
+        } else if result == ffi::AVERROR_EOF {
+            Ok(0)
+        } else {
+            Err(result)
+        }
+    }
+
+    pub fn write(&mut self, data: &[u8]) -> Result<(), c_int> {
+        assert!(data.len() <= (i32::MAX as usize));
+        unsafe {
+            ffi::avio_write(self.context, data.as_ptr(), data.len() as c_int);
+            self.error()
+        }
+    }
+
+    pub fn flush(&mut self) -> Result<(), c_int> {
+        unsafe {
+            ffi::avio_flush(self.context);
+            self.error()
+        }
+    }
+
+    /// `whence` is one of `SEEK_SET`, `SEEK_CUR` or `SEEK_END`. Returns the new position.
+    pub fn seek(&mut self, offset: i64, whence: c_int) -> Result<u64, c_int> {
+        let result = unsafe {
+            ffi::avio_seek(self.context, offset, whence)
+        };
+        if result >= 0 {
+            Ok(result as u64)
+        } else {
+            Err(result as c_int)
+        }
+    }
+
+    pub fn size(&self) -> Result<u64, c_int> {
+        let result = unsafe {
+            ffi::avio_size(self.context)
+        };
+        if result >= 0 {
+            Ok(result as u64)
+        } else {
+            Err(result as c_int)
+        }
+    }
+
+    pub fn is_seekable(&self) -> bool {
+        unsafe {
+            (*self.context).seekable != 0
+        }
+    }
+
+    unsafe fn error(&self) -> Result<(), c_int> {
+        let error = (*self.context).error;
+        if error < 0 {
+            Err(error)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// The largest encoded subtitle `write_subtitle` accepts.
+const SUBTITLE_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// The error returned by any call that was aborted by an `InterruptCallback`.
+pub const ERROR_INTERRUPTED: c_int = ffi::AVERROR_EXIT;
+
+/// Polled by FFmpeg during blocking operations; returning `true` aborts the operation with
+/// `ERROR_INTERRUPTED`.
+///
+/// NB: `AvioContextCallbacks` are not interrupted by this, only FFmpeg's own I/O and the demuxing
+/// loops around it.
+pub struct InterruptCallback {
+    callback: Box<FnMut() -> bool + Send>,
+}
+
+impl InterruptCallback {
+    pub fn new<F: FnMut() -> bool + Send + 'static>(callback: F) -> Self {
+        InterruptCallback {
+            callback: Box::new(callback),
+        }
+    }
+
+    /// Interrupts once `timeout_us` microseconds have elapsed from now.
+    pub fn timeout(timeout_us: i64) -> Self {
+        let deadline = unsafe { ffi::av_gettime_relative() } + timeout_us;
+        InterruptCallback::new(move || unsafe { ffi::av_gettime_relative() } >= deadline)
+    }
+
+    extern "C" fn cb_interrupt(opaque: *mut c_void) -> c_int {
+        unsafe {
+            let self_ = &mut *(opaque as *mut InterruptCallback);
+            if (self_.callback)() { 1 } else { 0 }
+        }
+    }
+
+    unsafe fn install(&mut self, context: *mut ffi::AVFormatContext) {
+        (*context).interrupt_callback = self.as_raw();
+    }
+
+    fn as_raw(&mut self) -> ffi::AVIOInterruptCB {
+        ffi::AVIOInterruptCB {
+            callback: Some(InterruptCallback::cb_interrupt),
+            opaque: self as *mut _ as *mut c_void,
+        }
+    }
+}
+
+/// The `fflags` option name of each `AVFMT_FLAG_*` that `OpenOptions` can set.
+const OPEN_FLAGS: [(c_int, &'static str); 8] = [
+    (ffi::AVFMT_FLAG_GENPTS, "genpts"),
+    (ffi::AVFMT_FLAG_IGNIDX, "ignidx"),
+    (ffi::AVFMT_FLAG_IGNDTS, "igndts"),
+    (ffi::AVFMT_FLAG_NOFILLIN, "nofillin"),
+    (ffi::AVFMT_FLAG_NOBUFFER, "nobuffer"),
+    (ffi::AVFMT_FLAG_DISCARD_CORRUPT, "discardcorrupt"),
+    (ffi::AVFMT_FLAG_SORT_DTS, "sortdts"),
+    (ffi::AVFMT_FLAG_KEEP_SIDE_DATA, "keepside"),
+];
+
+/// How `AvFormatContext::open_input_with` or `open_file_with` opens an input: the demuxer to
+/// use, an interrupt callback, a `SafeOpenPolicy` for untrusted input, and demuxer settings that
+/// must be in place before the input is opened.
+///
+/// Anything left unset keeps FFmpeg's default; flags are added to the default `fflags`.
+pub struct OpenOptions {
+    format: *mut ffi::AVInputFormat,
+    interrupt: Option<InterruptCallback>,
+    policy: Option<SafeOpenPolicy>,
+    probe_size: Option<i64>,
+    analyze_duration: Option<i64>,
+    fps_probe_size: Option<c_int>,
+    skip_initial_bytes: Option<i64>,
+    flags: c_int,
+}
+
+impl OpenOptions {
+    pub fn new() -> OpenOptions {
+        OpenOptions {
+            format: null_mut(),
+            interrupt: None,
+            policy: None,
+            probe_size: None,
+            analyze_duration: None,
+            fps_probe_size: None,
+            skip_initial_bytes: None,
+            flags: 0,
+        }
+    }
+
+    /// Opens the input with a specific demuxer instead of probing for one, which is necessary for
+    /// headerless formats such as raw PCM.
+    pub fn format(mut self, format: &AvInputFormat) -> OpenOptions {
+        self.format = format.format;
+        self
+    }
+
+    /// Installs `interrupt` from the start, so that even a stalled open (e.g. of a slow pipe or
+    /// network URL) can be aborted.
+    pub fn interrupt(mut self, interrupt: InterruptCallback) -> OpenOptions {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    /// Opens untrusted input under `policy`. The format is probed and checked before any demuxer
+    /// sees the input, and the streams are checked both before and after `find_stream_info`,
+    /// which has already been run on success. The policy's probing limits replace any set here.
+    pub fn policy(mut self, policy: SafeOpenPolicy) -> OpenOptions {
+        self.policy = Some(policy);
+        self
+    }
+
+    /// The most bytes read while detecting the format and its streams (`probesize2`).
+    pub fn probe_size(mut self, bytes: i64) -> OpenOptions {
+        self.probe_size = Some(bytes);
+        self
+    }
+
+    /// The most input, in `AV_TIME_BASE` units, decoded by `find_stream_info`
+    /// (`max_analyze_duration2`).
+    pub fn analyze_duration(mut self, duration: i64) -> OpenOptions {
+        self.analyze_duration = Some(duration);
+        self
+    }
+
+    /// The number of frames used to guess the frame rate; -1 for FFmpeg's default.
+    pub fn fps_probe_size(mut self, frames: c_int) -> OpenOptions {
+        self.fps_probe_size = Some(frames);
+        self
+    }
+
+    /// Skips this many bytes of the input before probing or demuxing it.
+    pub fn skip_initial_bytes(mut self, bytes: i64) -> OpenOptions {
+        self.skip_initial_bytes = Some(bytes);
+        self
+    }
+
+    /// Fills in missing timestamps, at the cost of reading ahead (`AVFMT_FLAG_GENPTS`).
+    pub fn generate_pts(mut self) -> OpenOptions {
+        self.flags |= ffi::AVFMT_FLAG_GENPTS;
+        self
+    }
+
+    /// Ignores the file's index, e.g. to seek by scanning a file whose index is broken.
+    pub fn ignore_index(mut self) -> OpenOptions {
+        self.flags |= ffi::AVFMT_FLAG_IGNIDX;
+        self
+    }
+
+    /// Ignores DTS on frames that also have a PTS.
+    pub fn ignore_dts(mut self) -> OpenOptions {
+        self.flags |= ffi::AVFMT_FLAG_IGNDTS;
+        self
+    }
+
+    /// Leaves missing values unfilled rather than guessing them from other timestamps.
+    pub fn no_fill_in(mut self) -> OpenOptions {
+        self.flags |= ffi::AVFMT_FLAG_NOFILLIN;
+        self
+    }
+
+    /// Doesn't buffer packets read by `find_stream_info`, which lowers latency for live input.
+    pub fn no_buffer(mut self) -> OpenOptions {
+        self.flags |= ffi::AVFMT_FLAG_NOBUFFER;
+        self
+    }
+
+    /// Drops packets that the demuxer marked as corrupt.
+    pub fn discard_corrupt(mut self) -> OpenOptions {
+        self.flags |= ffi::AVFMT_FLAG_DISCARD_CORRUPT;
+        self
+    }
+
+    /// Returns packets in DTS order where the demuxer can reorder them.
+    pub fn sort_dts(mut self) -> OpenOptions {
+        self.flags |= ffi::AVFMT_FLAG_SORT_DTS;
+        self
+    }
+
+    /// Keeps side data on packets instead of merging it into their payload.
+    pub fn keep_side_data(mut self) -> OpenOptions {
+        self.flags |= ffi::AVFMT_FLAG_KEEP_SIDE_DATA;
+        self
+    }
+
+    pub fn to_dictionary(&self) -> AvDictionary {
+        let mut dictionary = AvDictionary::new();
+        self.apply(&mut dictionary);
+        dictionary
+    }
+
+    /// Adds the demuxer settings to `dictionary`, replacing any it already has. The format,
+    /// interrupt callback and policy can't be passed as options.
+    pub fn apply(&self, dictionary: &mut AvDictionary) {
+        let settings = [
+            ("probesize", self.probe_size),
+            ("analyzeduration", self.analyze_duration),
+            ("fpsprobesize", self.fps_probe_size.map(|frames| frames as i64)),
+            ("skip_initial_bytes", self.skip_initial_bytes),
+        ];
+        for &(key, value) in settings.iter() {
+            if let Some(value) = value {
+                dictionary.set(key, &value.to_string());
+            }
+        }
+
+        // Each `+name` sets a flag without clearing the defaults.
+        let mut flags = String::new();
+        for &(flag, name) in OPEN_FLAGS.iter() {
+            if self.flags & flag != 0 {
+                flags.push('+');
+                flags.push_str(name);
+            }
+        }
+        if !flags.is_empty() {
+            dictionary.set("fflags", &flags);
+        }
+    }
+
+    /// Applies the settings and the policy to `options`, and returns the demuxer to open `pb`
+    /// with, probing and checking it first under a policy.
+    fn prepare(&self, pb: *mut ffi::AVIOContext, options: &mut AvDictionary)
+               -> Result<*mut ffi::AVInputFormat, SafeOpenError> {
+        self.apply(options);
+        let policy = match self.policy {
+            Some(ref policy) => policy,
+            None => return Ok(self.format),
+        };
+
+        let format = if self.format.is_null() {
+            try!(policy.probe(pb)).format
+        } else {
+            try!(policy.check_format(&AvInputFormat { format: self.format }.name()));
+            self.format
+        };
+        policy.apply(options);
+        Ok(format)
+    }
+}
+
+/// Demuxers that open other files or URLs named by the input: playlists, concat lists, image
+/// sequences and session descriptions.
+const EXTERNAL_REFERENCE_FORMATS: [&'static str; 7] = [
+    "hls", "applehttp", "concat", "image2", "dash", "sdp", "rtsp",
+];
+
+/// The MP4 demuxer, which follows `dref` references to other files unless `enable_drefs` turns
+/// that off, an option FFmpeg 2.x doesn't have.
+const MOV_FORMAT: &'static str = "mov";
+
+/// Why `AvFormatContext::open_input_with`, `open_file_with` or one of the `_safe` opens failed.
+/// Everything but `Ffmpeg` comes from a `SafeOpenPolicy`.
+#[derive(Debug)]
+pub enum SafeOpenError {
+    /// FFmpeg failed to open or probe the input; an `AVERROR` code.
+    Ffmpeg(c_int),
+    /// The input was detected as a format outside `SafeOpenPolicy::formats`.
+    FormatNotAllowed(String),
+    /// The input was detected as a format that refers to other files or URLs.
+    ExternalReferences(String),
+    /// A stream needs a decoder outside `SafeOpenPolicy::codecs`.
+    CodecNotAllowed { stream: usize, codec: String },
+    /// A stream or decoded frame is larger than `SafeOpenPolicy::max_resolution`.
+    ResolutionTooLarge { stream: usize, width: c_int, height: c_int },
+    /// One decoded frame of every stream together would take more than
+    /// `SafeOpenPolicy::max_frame_memory`.
+    FrameMemoryTooLarge { bytes: u64, max: u64 },
+    /// This version of FFmpeg lacks the named option the policy is enforced with.
+    Unsupported(&'static str),
+}
+
+impl SafeOpenError {
+    /// The `AVERROR` code to report the error with where only a code fits: the FFmpeg error
+    /// itself, `AVERROR(ENOSYS)` if unsupported, and `AVERROR(EACCES)` for refusals by the policy.
+    pub fn to_averror(&self) -> c_int {
+        match *self {
+            SafeOpenError::Ffmpeg(err) => err,
+            SafeOpenError::Unsupported(_) => -ENOSYS,
+            _ => -EACCES,
+        }
+    }
+}
+
+/// Restrictions for opening untrusted input, such as user uploads, set with `OpenOptions::policy`.
+///
+/// Inputs are only ever opened through custom I/O (the caller's, or a local file read by this
+/// crate), so the input can't name a URL for FFmpeg's protocols to open. Demuxers that follow
+/// references to other files (HLS, concat, image2, ...) are refused, and so is MP4 unless
+/// `allow_mov` is set. By default probing is capped at FFmpeg's usual 5 MB and 5 seconds;
+/// anything else left unset is not limited.
+#[derive(Clone)]
+pub struct SafeOpenPolicy {
+    formats: Option<Vec<String>>,
+    allow_mov: bool,
+    codecs: Option<Vec<String>>,
+    probe_size: usize,
+    analyze_duration: i64,
+    max_resolution: Option<(c_int, c_int)>,
+    max_frame_memory: Option<u64>,
+}
+
+impl SafeOpenPolicy {
+    pub fn new() -> SafeOpenPolicy {
+        SafeOpenPolicy {
+            formats: None,
+            allow_mov: false,
+            codecs: None,
+            probe_size: 5000000,
+            analyze_duration: 5 * ffi::AV_TIME_BASE as i64,
+            max_resolution: None,
+            max_frame_memory: None,
+        }
+    }
+
+    /// Only allows the named demuxers, e.g. `&["matroska", "mp4", "ogg"]`. A demuxer with several
+    /// names (`"mov,mp4,m4a,3gp,3g2,mj2"`) is allowed if any of them is listed.
+    pub fn formats(mut self, formats: &[&str]) -> SafeOpenPolicy {
+        self.formats = Some(formats.iter().map(|format| format.to_string()).collect());
+        self
+    }
+
+    /// Allows the MP4 demuxer (`mov`), if `formats` does too.
+    ///
+    /// NB: FFmpeg 2.x can't stop it from following `dref` atoms, which name other files to read
+    /// samples from. With `use_absolute_path` off, as set here, only paths relative to the
+    /// input's directory are tried, but custom I/O has no directory, so they resolve against the
+    /// working directory. Only allow MP4 where that holds nothing a user mustn't read.
+    pub fn allow_mov(mut self) -> SafeOpenPolicy {
+        self.allow_mov = true;
+        self
+    }
+
+    /// Only allows the named decoders, e.g. `&["h264", "aac"]`.
+    pub fn codecs(mut self, codecs: &[&str]) -> SafeOpenPolicy {
+        self.codecs = Some(codecs.iter().map(|codec| codec.to_string()).collect());
+        self
+    }
+
+    /// The most bytes read while detecting the format and its streams.
+    pub fn probe_size(mut self, bytes: usize) -> SafeOpenPolicy {
+        self.probe_size = bytes;
+        self
+    }
+
+    /// The most input, in `AV_TIME_BASE` units, decoded by `find_stream_info`.
+    pub fn analyze_duration(mut self, duration: i64) -> SafeOpenPolicy {
+        self.analyze_duration = duration;
+        self
+    }
+
+    /// Rejects video streams wider or taller than this when opening. FFmpeg 2.x decoders can't be
+    /// limited themselves, and frames may change size mid-stream, so check each decoded frame
+    /// with `check_frame` too.
+    pub fn max_resolution(mut self, width: c_int, height: c_int) -> SafeOpenPolicy {
+        self.max_resolution = Some((width, height));
+        self
+    }
+
+    /// Rejects inputs where one decoded frame of every stream together takes more than `bytes`,
+    /// as estimated from each stream's format and dimensions when opening.
+    ///
+    /// NB: This is an estimate of one frame per stream, not a limit on memory use. FFmpeg can't
+    /// limit its allocations per input, so frames buffered by decoders (e.g. for B-frame
+    /// reordering) or by the caller, and demuxer overhead, are not counted.
+    pub fn max_frame_memory(mut self, bytes: u64) -> SafeOpenPolicy {
+        self.max_frame_memory = Some(bytes);
+        self
+    }
+
+    /// Checks a demuxer name, as given by `AvInputFormat::name`, against the policy.
+    pub fn check_format(&self, name: &str) -> Result<(), SafeOpenError> {
+        if self.refers_to_files(name) {
+            return Err(SafeOpenError::ExternalReferences(name.to_string()))
+        }
+        if let Some(ref formats) = self.formats {
+            if !name.split(',').any(|name| formats.iter().any(|format| *format == name)) {
+                return Err(SafeOpenError::FormatNotAllowed(name.to_string()))
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the decoder, resolution and frame memory of every stream. Dimensions and formats
+    /// are only known once the input has been opened, and sometimes only after
+    /// `find_stream_info`; streams are not held against what isn't known yet.
+    pub fn check_streams<T>(&self, context: &AvFormatContext<T>) -> Result<(), SafeOpenError> {
+        let mut frame_memory = 0u64;
+        unsafe {
+            let context = &*context.context;
+            for i in 0..context.nb_streams {
+                let stream = *context.streams.offset(i as isize);
+                if stream.is_null() || (*stream).codec.is_null() {
+                    continue
+                }
+                let codec = ffi::EitherAVCodecContext::from_ptr((*stream).codec);
+
+                // Streams without a decoder can't be decoded in the first place.
+                if let Some(ref codecs) = self.codecs {
+                    if let Ok(decoder) = AvCodec::find_decoder(*ffmpeg_ffi_avcodeccontext_field!(codec, codec_id)) {
+                        let name = decoder.name();
+                        if !codecs.iter().any(|codec| *codec == name) {
+                            return Err(SafeOpenError::CodecNotAllowed {
+                                stream: i as usize,
+                                codec: name,
+                            })
+                        }
+                    }
+                }
+
+                if let Some((max_width, max_height)) = self.max_resolution {
+                    let width = *ffmpeg_ffi_avcodeccontext_field!(codec, width);
+                    let height = *ffmpeg_ffi_avcodeccontext_field!(codec, height);
+                    if width > max_width || height > max_height {
+                        return Err(SafeOpenError::ResolutionTooLarge {
+                            stream: i as usize,
+                            width: width,
+                            height: height,
+                        })
+                    }
+                }
+
+                frame_memory += frame_size(codec);
+            }
+        }
+
+        match self.max_frame_memory {
+            Some(max) if frame_memory > max => Err(SafeOpenError::FrameMemoryTooLarge {
+                bytes: frame_memory,
+                max: max,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks a frame decoded from stream `stream` against `max_resolution`.
+    pub fn check_frame(&self, stream: usize, frame: &AvFrame) -> Result<(), SafeOpenError> {
+        match self.max_resolution {
+            Some((max_width, max_height)) if frame.width() > max_width || frame.height() > max_height => {
+                Err(SafeOpenError::ResolutionTooLarge {
+                    stream: stream,
+                    width: frame.width(),
+                    height: frame.height(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Adds the policy's format, codec and probing limits to the options of an input being
+    /// opened through custom I/O, replacing any it already has.
+    pub fn apply(&self, options: &mut AvDictionary) {
+        // There is no format blacklist, so everything but the referencing demuxers is listed.
+        let formats = match self.formats {
+            Some(ref formats) => formats.clone(),
+            None => demuxers().map(|format| format.name()).filter(|name| !self.refers_to_files(name)).collect(),
+        };
+        options.set("format_whitelist", &formats.connect(","));
+        if let Some(ref codecs) = self.codecs {
+            options.set("codec_whitelist", &codecs.connect(","));
+        }
+        if self.allow_mov {
+            options.set("use_absolute_path", "0");
+        }
+        options.set("probesize", &self.probe_size.to_string());
+        options.set("analyzeduration", &self.analyze_duration.to_string());
+    }
+
+    /// Adds the policy's codec limits to the options of a decoder being opened, e.g. through
+    /// `AvFormatContext::open_stream`. Resolution is checked with `check_frame` instead.
+    pub fn apply_to_decoder(&self, options: &mut AvDictionary) {
+        if let Some(ref codecs) = self.codecs {
+            options.set("codec_whitelist", &codecs.connect(","));
+        }
+    }
+
+    fn refers_to_files(&self, name: &str) -> bool {
+        name.split(',').any(|name| {
+            EXTERNAL_REFERENCE_FORMATS.contains(&name) || (name == MOV_FORMAT && !self.allow_mov)
+        })
+    }
+
+    fn probe(&self, pb: *mut ffi::AVIOContext) -> Result<AvInputFormat, SafeOpenError> {
+        let (format, _) = try!(AvInputFormat::probe_raw(pb, self.probe_size).map_err(SafeOpenError::Ffmpeg));
+        try!(self.check_format(&format.name()));
+        Ok(format)
+    }
+}
+
+/// The bytes taken by one decoded frame of a stream, or 0 if its format isn't known yet.
+unsafe fn frame_size(codec: ffi::EitherAVCodecContext) -> u64 {
+    let size = match *ffmpeg_ffi_avcodeccontext_field!(codec, codec_type) {
+        ffi::AVMEDIA_TYPE_VIDEO => ffi::avpicture_get_size(*ffmpeg_ffi_avcodeccontext_field!(codec, pix_fmt),
+                                                           *ffmpeg_ffi_avcodeccontext_field!(codec, width),
+                                                           *ffmpeg_ffi_avcodeccontext_field!(codec, height)),
+        ffi::AVMEDIA_TYPE_AUDIO => ffi::av_samples_get_buffer_size(null_mut(),
+                                                                   *ffmpeg_ffi_avcodeccontext_field!(codec, channels),
+                                                                   *ffmpeg_ffi_avcodeccontext_field!(codec, frame_size),
+                                                                   *ffmpeg_ffi_avcodeccontext_field!(codec, sample_fmt),
+                                                                   1),
+        _ => 0,
+    };
+    if size > 0 { size as u64 } else { 0 }
+}
+
+/// A flag that can be shared with another thread to cancel a blocking open or read.
+#[derive(Clone)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        CancelToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    pub fn interrupt_callback(&self) -> InterruptCallback {
+        let token = self.clone();
+        InterruptCallback::new(move || token.is_cancelled())
+    }
+
+    /// Interrupts when cancelled or once `timeout_us` microseconds have elapsed from now,
+    /// whichever comes first.
+    pub fn interrupt_callback_with_timeout(&self, timeout_us: i64) -> InterruptCallback {
+        let token = self.clone();
+        let mut timeout = InterruptCallback::timeout(timeout_us);
+        InterruptCallback::new(move || token.is_cancelled() || (timeout.callback)())
+    }
+}
+
+/// Cover art embedded in a file, e.g. an ID3 `APIC` frame or an MP4 `covr` atom.
+pub struct AttachedPicture {
+    pub stream_index: usize,
+    pub codec_id: AvCodecId,
+    pub data: Vec<u8>,
+}
+
+impl AttachedPicture {
+    pub fn mime_type(&self) -> Option<&'static str> {
+        match CodecId::from_id(self.codec_id) {
+            Some(CodecId::Mjpeg) => Some("image/jpeg"),
+            Some(CodecId::Png) => Some("image/png"),
+            Some(CodecId::Gif) => Some("image/gif"),
+            Some(CodecId::Bmp) => Some("image/bmp"),
+            Some(CodecId::Tiff) => Some("image/tiff"),
+            Some(CodecId::Webp) => Some("image/webp"),
+            _ => None,
+        }
+    }
+
+    /// Decodes the picture with a standalone decoder, leaving the stream's own context untouched.
+    ///
+    /// Returns `AVERROR_INVALIDDATA` if the data decodes to no picture at all.
+    pub fn decode(&self) -> Result<AvFrame, c_int> {
+        let decoder = try!(AvCodec::find_decoder(self.codec_id).or_else(|_| Err(-1)));
+        let context = AvCodecContext::new(&decoder);
+        // The frame outlives the decoder, so it must own its data rather than point into the
+        // decoder's buffers.
+        let mut options = AvDictionary::new();
+        options.set("refcounted_frames", "1");
+        let (result, _) = context.open(&decoder, options);
+        try!(result.or_else(|_| Err(-1)));
+
+        let mut data = Vec::with_capacity(self.data.len() + ffi::FF_INPUT_BUFFER_PADDING_SIZE);
+        data.push_all(&self.data);
+        for _ in 0..ffi::FF_INPUT_BUFFER_PADDING_SIZE {
+            data.push(0);
+        }
+
+        let frame = AvFrame::new();
+        let mut packet = AvPacket::new(&mut data);
+        let mut got_picture = 0;
+        let result = unsafe {
+            ffi::avcodec_decode_video2(context.context.ptr(),
+                                       frame.frame,
+                                       &mut got_picture,
+                                       packet.packet.ptr())
+        };
+        if result < 0 {
+            Err(result)
+        } else if got_picture == 0 {
+            Err(ffi::AVERROR_INVALIDDATA)
+        } else {
+            Ok(frame)
+        }
+    }
+}
+
+pub struct AvChapter<'a> {
+    chapter: *mut ffi::AVChapter,
+    _marker: PhantomData<&'a ffi::AVChapter>,
+}
+
+impl<'a> AvChapter<'a> {
+    pub fn id(&self) -> c_int {
+        unsafe {
+            (*self.chapter).id
+        }
+    }
+
+    pub fn time_base(&self) -> ffi::AVRational {
+        unsafe {
+            (*self.chapter).time_base
+        }
+    }
+
+    /// In units of `time_base`.
+    pub fn start(&self) -> i64 {
+        unsafe {
+            (*self.chapter).start
+        }
+    }
+
+    /// In units of `time_base`.
+    pub fn end(&self) -> i64 {
+        unsafe {
+            (*self.chapter).end
+        }
+    }
+
+    pub fn metadata(&self) -> AvDictionary {
+        unsafe {
+            AvDictionary::from_raw((*self.chapter).metadata)
+        }
+    }
+
+    pub fn title(&self) -> Option<String> {
+        self.metadata().get("title")
+    }
+}
+
+pub struct AvProgram<'a> {
+    program: *mut ffi::AVProgram,
+    _marker: PhantomData<&'a ffi::AVProgram>,
+}
+
+impl<'a> AvProgram<'a> {
+    pub fn id(&self) -> c_int {
+        unsafe {
+            (*self.program).id
+        }
+    }
+
+    /// The MPEG-TS program number, as listed in the PAT.
+    pub fn program_number(&self) -> c_int {
         unsafe {
-            let self_ = AvioContext::<C>::cb_userdata(userdata);
-            avio_transform_result(self_.callbacks.read(from_raw_parts_mut(data, size as usize)), -1, |v| v as c_int)
+            (*self.program).program_num
         }
     }
 
-    extern "C" fn cb_write(userdata: *mut c_void, data: *mut u8, size: c_int) -> c_int {
+    pub fn pmt_pid(&self) -> c_int {
         unsafe {
-            let self_ = AvioContext::<C>::cb_userdata(userdata);
-            avio_transform_result(self_.callbacks.write(from_raw_parts(data, size as usize)), -1, |v| v as c_int)
+            (*self.program).pmt_pid
         }
     }
 
-    extern "C" fn cb_seek(userdata: *mut c_void, position: i64, whence: c_int) -> i64 {
+    pub fn pcr_pid(&self) -> c_int {
         unsafe {
-            let self_ = AvioContext::<C>::cb_userdata(userdata);
-            avio_transform_result(self_.callbacks.seek(position, whence), -1, |v| v as i64)
+            (*self.program).pcr_pid
         }
     }
 
-    pub fn new(buffer_size: usize, writable: bool, callbacks: C) -> Box<Self> {
+    pub fn stream_indices(&self) -> Vec<usize> {
         unsafe {
-            let mut self_ = Box::new(AvioContext {
-                context: null_mut(),
-                buffer: ffi::av_malloc(buffer_size as size_t),
-                callbacks: callbacks,
-            });
-
-            self_.context = ffi::avio_alloc_context(self_.buffer as *mut _,
-                                                    buffer_size as c_int,
-                                                    if writable { 1 } else { 0 },
-                                                    &mut *self_ as *mut _ as *mut c_void,
-                                                    Some(AvioContext::<C>::cb_read),
-                                                    Some(AvioContext::<C>::cb_write),
-                                                    Some(AvioContext::<C>::cb_seek));
+            let program = &*self.program;
+            (0..program.nb_stream_indexes).map(|i| *program.stream_index.offset(i as isize) as usize).collect()
+        }
+    }
 
-            self_
+    pub fn metadata(&self) -> AvDictionary {
+        unsafe {
+            AvDictionary::from_raw((*self.program).metadata)
         }
     }
 }
 
-pub struct AvioContextReader<R> {
-    reader: R,
+pub struct AvChapters<'a> {
+    context: &'a ffi::AVFormatContext,
+    index: c_uint,
 }
 
-impl<R> AvioContextReader<R> {
-    pub fn new(reader: R) -> Self {
-        AvioContextReader {
-            reader: reader,
+impl<'a> Iterator for AvChapters<'a> {
+    type Item = AvChapter<'a>;
+
+    fn next(&mut self) -> Option<AvChapter<'a>> {
+        while self.index < self.context.nb_chapters {
+            let chapter = unsafe { *self.context.chapters.offset(self.index as isize) };
+            self.index += 1;
+            if !chapter.is_null() {
+                return Some(AvChapter {
+                    chapter: chapter,
+                    _marker: PhantomData,
+                })
+            }
         }
+        None
     }
 }
 
-impl<R: Reader> AvioContextCallbacks for AvioContextReader<R> {
-    fn read(&mut self, data: &mut [u8]) -> Result<usize, ()> {
-        self.reader.read(data).or_else(|_| Err(()))
+pub struct AvPrograms<'a> {
+    context: &'a ffi::AVFormatContext,
+    index: c_uint,
+}
+
+impl<'a> Iterator for AvPrograms<'a> {
+    type Item = AvProgram<'a>;
+
+    fn next(&mut self) -> Option<AvProgram<'a>> {
+        while self.index < self.context.nb_programs {
+            let program = unsafe { *self.context.programs.offset(self.index as isize) };
+            self.index += 1;
+            if !program.is_null() {
+                return Some(AvProgram {
+                    program: program,
+                    _marker: PhantomData,
+                })
+            }
+        }
+        None
     }
 }
 
 pub struct AvFormatContext<T = ()> {
     pub context: *mut ffi::AVFormatContext,
     _avio: Option<Box<T>>,
+    interrupt: Option<Box<InterruptCallback>>,
 }
 
-impl<T> AvFormatContext<AvioContext<T>> {
-    pub fn open_input(avio: Box<AvioContext<T>>, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
+// Demuxing and muxing are single-threaded, so the context can move between threads as long as
+// its I/O can.
+unsafe impl<T: Send> Send for AvFormatContext<T> {}
+
+/// I/O that an `AvFormatContext` can demux in place of a file: an `AvioContext` over callbacks, or
+/// an `Avio` opened through FFmpeg's protocols.
+pub trait CustomIo {
+    fn io_context(&self) -> *mut ffi::AVIOContext;
+}
+
+impl<T> CustomIo for AvioContext<T> {
+    fn io_context(&self) -> *mut ffi::AVIOContext {
+        self.context
+    }
+}
+
+impl CustomIo for Avio {
+    fn io_context(&self) -> *mut ffi::AVIOContext {
+        self.context
+    }
+}
+
+impl<I: CustomIo> AvFormatContext<I> {
+    pub fn open_input(io: Box<I>, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
+        AvFormatContext::open_io(io, null_mut(), None, options)
+    }
+
+    pub fn open_input_with(io: Box<I>, open_options: OpenOptions, mut options: AvDictionary)
+                           -> (Result<Self, SafeOpenError>, AvDictionary) {
+        let format = match open_options.prepare(io.io_context(), &mut options) {
+            Ok(format) => format,
+            Err(error) => return (Err(error), options),
+        };
+        let OpenOptions { interrupt, policy, .. } = open_options;
+        AvFormatContext::check_opened(AvFormatContext::open_io(io, format, interrupt, options), policy.as_ref())
+    }
+
+    fn open_io(io: Box<I>, format: *mut ffi::AVInputFormat, interrupt: Option<InterruptCallback>, options: AvDictionary)
+               -> (Result<Self, c_int>, AvDictionary) {
         unsafe {
             let context = ffi::avformat_alloc_context();
             (*context).flags |= ffi::AVFMT_FLAG_CUSTOM_IO;
+            (*context).pb = io.io_context();
+            AvFormatContext::open_internal(context, Some(io), "", format, interrupt, options)
+        }
+    }
+}
+
+impl<T> AvFormatContext<AvioContext<T>> {
+    /// Opens the input with a specific demuxer instead of probing for one, which is necessary for
+    /// headerless formats such as raw PCM.
+    pub fn open_input_with_format(avio: Box<AvioContext<T>>, format: &AvInputFormat, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
+        AvFormatContext::open_io(avio, format.format, None, options)
+    }
+
+    pub fn open_input_interruptible(avio: Box<AvioContext<T>>, interrupt: InterruptCallback, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
+        AvFormatContext::open_io(avio, null_mut(), Some(interrupt), options)
+    }
+
+    /// Opens untrusted input under `policy`, like `open_input_with` given `OpenOptions::policy`.
+    pub fn open_input_safe(avio: Box<AvioContext<T>>, policy: &SafeOpenPolicy, options: AvDictionary)
+                           -> (Result<Self, SafeOpenError>, AvDictionary) {
+        AvFormatContext::open_input_with(avio, OpenOptions::new().policy(policy.clone()), options)
+    }
+
+    /// Creates a muxing context writing to `avio`, using the named muxer (e.g. `"matroska"`).
+    pub fn open_output(avio: Box<AvioContext<T>>, format_name: &str) -> Result<Self, c_int> {
+        unsafe {
+            let context = try!(AvFormatContext::<AvioContext<T>>::alloc_output(Some(format_name), ""));
+            (*context).flags |= ffi::AVFMT_FLAG_CUSTOM_IO;
             (*context).pb = avio.context;
-            AvFormatContext::open_internal(context, Some(avio), "", options)
+            Ok(AvFormatContext {
+                context: context,
+                _avio: Some(avio),
+                interrupt: None,
+            })
+        }
+    }
+
+    /// Takes the I/O error behind the last failure, if the callbacks reported one.
+    pub fn take_io_error(&mut self) -> Option<IoError> {
+        self._avio.as_mut().and_then(|avio| avio.take_error())
+    }
+}
+
+
+impl AvFormatContext<Avio> {
+    pub fn open_input_avio(avio: Avio, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
+        AvFormatContext::open_input(Box::new(avio), options)
+    }
+
+    /// Muxes into an `Avio` opened with `AVIO_FLAG_WRITE`, e.g. to stream over a network
+    /// protocol with options of its own.
+    pub fn open_output_avio(avio: Avio, format_name: &str) -> Result<Self, c_int> {
+        unsafe {
+            let context = try!(AvFormatContext::<Avio>::alloc_output(Some(format_name), ""));
+            (*context).flags |= ffi::AVFMT_FLAG_CUSTOM_IO;
+            (*context).pb = avio.context;
+            Ok(AvFormatContext {
+                context: context,
+                _avio: Some(Box::new(avio)),
+                interrupt: None,
+            })
         }
     }
 }
 
+/// A local file read by this crate rather than FFmpeg's `file:` protocol, which is how files are
+/// opened under a `SafeOpenPolicy`.
+pub type FileInput = AvioContext<AvioContextSeekableReader<File>>;
+
+impl AvFormatContext<FileInput> {
+    /// Opens `filename` like `open_file`. Under a policy the file is opened and read by this
+    /// crate instead, by name only, so neither URLs nor image2 patterns such as `%d` are
+    /// expanded.
+    pub fn open_file_with(filename: &str, open_options: OpenOptions, mut options: AvDictionary)
+                          -> (Result<Self, SafeOpenError>, AvDictionary) {
+        if open_options.policy.is_some() {
+            let file = match File::open(&Path::new(filename)) {
+                Ok(file) => file,
+                Err(error) => return (Err(SafeOpenError::Ffmpeg(io_error_to_averror(&error))), options),
+            };
+            let avio = AvioContext::new(0x1000, false, AvioContextSeekableReader::new(file));
+            return AvFormatContext::open_input_with(avio, open_options, options)
+        }
+
+        open_options.apply(&mut options);
+        let OpenOptions { format, interrupt, .. } = open_options;
+        let opened = unsafe {
+            AvFormatContext::open_internal(ffi::avformat_alloc_context(), None, filename, format, interrupt, options)
+        };
+        AvFormatContext::check_opened(opened, None)
+    }
+
+    /// Opens an untrusted local file under `policy`, like `open_file_with` given
+    /// `OpenOptions::policy`.
+    pub fn open_file_safe(filename: &str, policy: &SafeOpenPolicy, options: AvDictionary)
+                          -> (Result<Self, SafeOpenError>, AvDictionary) {
+        AvFormatContext::open_file_with(filename, OpenOptions::new().policy(policy.clone()), options)
+    }
+}
 
 impl<T> AvFormatContext<T> {
     pub fn open_file(filename: &str, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
         unsafe {
-            AvFormatContext::open_internal(ffi::avformat_alloc_context(), None, filename, options)
+            AvFormatContext::open_internal(ffi::avformat_alloc_context(), None, filename, null_mut(), None, options)
+        }
+    }
+
+    pub fn open_file_with_format(filename: &str, format: &AvInputFormat, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
+        unsafe {
+            AvFormatContext::open_internal(ffi::avformat_alloc_context(), None, filename, format.format, None, options)
+        }
+    }
+
+    /// Opens the file with `interrupt` installed from the start, so that even a stalled open (e.g.
+    /// of a slow pipe or network URL) can be aborted.
+    pub fn open_file_interruptible(filename: &str, interrupt: InterruptCallback, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
+        unsafe {
+            AvFormatContext::open_internal(ffi::avformat_alloc_context(), None, filename, null_mut(), Some(interrupt), options)
+        }
+    }
+
+    /// Creates a muxing context writing to `filename`. The muxer is guessed from the extension
+    /// unless `format_name` is given.
+    pub fn open_output_file(filename: &str, format_name: Option<&str>) -> Result<Self, c_int> {
+        unsafe {
+            let context = try!(AvFormatContext::<T>::alloc_output(format_name, filename));
+            if (*(*context).oformat).flags & ffi::AVFMT_NOFILE == 0 {
+                let filename = try!(CString::new(filename).or_else(|_| Err(ERROR_CSTRING)));
+                let result = ffi::avio_open(&mut (*context).pb, filename.as_ptr(), ffi::AVIO_FLAG_WRITE);
+                if result < 0 {
+                    ffi::avformat_free_context(context);
+                    return Err(result)
+                }
+            }
+
+            // `avformat_close_input` also closes `pb` for output contexts that own it.
+            Ok(AvFormatContext {
+                context: context,
+                _avio: None,
+                interrupt: None,
+            })
+        }
+    }
+
+    unsafe fn alloc_output(format_name: Option<&str>, filename: &str) -> Result<*mut ffi::AVFormatContext, c_int> {
+        let format_name = match format_name.map(|name| CString::new(name)) {
+            Some(Ok(name)) => Some(name),
+            Some(Err(_)) => return Err(ERROR_CSTRING),
+            None => None,
+        };
+        let filename = try!(CString::new(filename).or_else(|_| Err(ERROR_CSTRING)));
+
+        let mut context = null_mut();
+        let result = ffi::avformat_alloc_output_context2(&mut context,
+                                                         null_mut(),
+                                                         format_name.as_ref().map(|name| name.as_ptr()).unwrap_or(null()),
+                                                         filename.as_ptr());
+        if result >= 0 && !context.is_null() {
+            Ok(context)
+        } else {
+            Err(result)
         }
     }
 
-    fn open_internal(context: *mut ffi::AVFormatContext, avio: Option<Box<T>>, filename: &str, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
+    fn open_internal(context: *mut ffi::AVFormatContext, avio: Option<Box<T>>, filename: &str, format: *mut ffi::AVInputFormat, interrupt: Option<InterruptCallback>, options: AvDictionary) -> (Result<Self, c_int>, AvDictionary) {
         unsafe {
             let mut context = context;
+            let mut interrupt = interrupt.map(Box::new);
+            if let Some(ref mut interrupt) = interrupt {
+                interrupt.install(context);
+            }
+
             let mut options_not_found = options.dictionary;
-            let result = ffi::avformat_open_input(&mut context, CString::new(filename).unwrap().as_ptr(), null_mut(), &mut options_not_found);
+            let result = ffi::avformat_open_input(&mut context, CString::new(filename).unwrap().as_ptr(), format, &mut options_not_found);
 
             forget(options);
             let options_not_found = AvDictionary {
@@ -145,6 +1852,7 @@ impl<T> AvFormatContext<T> {
                 (Ok(AvFormatContext {
                     context: context,
                     _avio: avio,
+                    interrupt: interrupt,
                 }), options_not_found)
             } else {
                 (Err(result), options_not_found)
@@ -152,6 +1860,43 @@ impl<T> AvFormatContext<T> {
         }
     }
 
+    fn check_opened(opened: (Result<Self, c_int>, AvDictionary), policy: Option<&SafeOpenPolicy>)
+                    -> (Result<Self, SafeOpenError>, AvDictionary) {
+        let (context, options_not_found) = opened;
+        let context = match context {
+            Ok(context) => context,
+            Err(error) => return (Err(SafeOpenError::Ffmpeg(error)), options_not_found),
+        };
+        let policy = match policy {
+            Some(policy) => policy,
+            None => return (Ok(context), options_not_found),
+        };
+        if let Some(option) = unapplied_restriction(&options_not_found) {
+            return (Err(SafeOpenError::Unsupported(option)), options_not_found)
+        }
+
+        // Streams whose headers already give their dimensions are refused before any of their
+        // frames are decoded; the rest are decoded under the codec limits of `apply_to_decoder`,
+        // and checked once `find_stream_info` has found their dimensions.
+        if let Err(error) = policy.check_streams(&context) {
+            return (Err(error), options_not_found)
+        }
+        let mut decoder_options = AvDictionary::new();
+        policy.apply_to_decoder(&mut decoder_options);
+        let result = context.find_stream_info_for_all(&decoder_options);
+        let result = result.map_err(SafeOpenError::Ffmpeg).and_then(|_| policy.check_streams(&context));
+        (result.map(|_| context), options_not_found)
+    }
+
+    /// Replaces the interrupt callback for all subsequent blocking calls on this context.
+    pub fn set_interrupt_callback(&mut self, interrupt: InterruptCallback) {
+        let mut interrupt = Box::new(interrupt);
+        unsafe {
+            interrupt.install(self.context);
+        }
+        self.interrupt = Some(interrupt);
+    }
+
     pub fn find_stream_info(&self, options: AvDictionary) -> (Result<(), c_int>, AvDictionary) {
         unsafe {
             let mut options_not_found = options.dictionary;
@@ -170,6 +1915,109 @@ impl<T> AvFormatContext<T> {
         }
     }
 
+    /// Like `find_stream_info`, but with a copy of `options` for every stream rather than just
+    /// the first.
+    fn find_stream_info_for_all(&self, options: &AvDictionary) -> Result<(), c_int> {
+        unsafe {
+            let streams = (*self.context).nb_streams as usize;
+            let mut dictionaries: Vec<_> = (0..streams).map(|_| {
+                let mut dictionary = null_mut();
+                ffi::av_dict_copy(&mut dictionary, options.dictionary, 0);
+                dictionary
+            }).collect();
+            let result = ffi::avformat_find_stream_info(self.context,
+                                                        if streams > 0 { dictionaries.as_mut_ptr() } else { null_mut() });
+            for dictionary in dictionaries.iter_mut() {
+                ffi::av_dict_free(dictionary);
+            }
+
+            if result >= 0 {
+                Ok(())
+            } else {
+                Err(result)
+            }
+        }
+    }
+
+    pub fn chapters<'a>(&'a self) -> AvChapters<'a> {
+        AvChapters {
+            context: unsafe { &*self.context },
+            index: 0,
+        }
+    }
+
+    pub fn programs<'a>(&'a self) -> AvPrograms<'a> {
+        AvPrograms {
+            context: unsafe { &*self.context },
+            index: 0,
+        }
+    }
+
+    pub fn stream_time_base(&self, index: usize) -> ffi::AVRational {
+        unsafe {
+            let context = &*self.context;
+            assert!((index as c_uint) < context.nb_streams);
+            (**context.streams.offset(index as isize)).time_base
+        }
+    }
+
+    /// A combination of the `AV_DISPOSITION_*` flags.
+    pub fn stream_disposition(&self, index: usize) -> c_int {
+        unsafe {
+            let context = &*self.context;
+            assert!((index as c_uint) < context.nb_streams);
+            (**context.streams.offset(index as isize)).disposition
+        }
+    }
+
+    /// Side data that applies to the whole stream, such as the display matrix or HDR metadata.
+    pub fn stream_side_data(&self, index: usize) -> Vec<SideData> {
+        unsafe {
+            let context = &*self.context;
+            assert!((index as c_uint) < context.nb_streams);
+            let stream = &**context.streams.offset(index as isize);
+            SideData::from_packet_list(stream.side_data, stream.nb_side_data)
+        }
+    }
+
+    /// The counterclockwise rotation in degrees from the stream's display matrix, if it has one.
+    pub fn stream_rotation(&self, index: usize) -> Option<f64> {
+        self.stream_side_data(index).iter().filter_map(|side_data| match *side_data {
+            SideData::DisplayMatrix(ref matrix) => Some(matrix.rotation()),
+            _ => None,
+        }).next()
+    }
+
+    /// Returns a copy of every picture stored in a stream marked `AV_DISPOSITION_ATTACHED_PIC`.
+    ///
+    /// These are available as soon as the input is opened; no packets need to be read.
+    pub fn attached_pictures(&self) -> Vec<AttachedPicture> {
+        let mut pictures = Vec::new();
+        unsafe {
+            let context = &*self.context;
+            for i in 0..context.nb_streams {
+                let stream = *context.streams.offset(i as isize);
+                if stream.is_null() || (*stream).disposition & ffi::AV_DISPOSITION_ATTACHED_PIC == 0 {
+                    continue
+                }
+
+                let packet = &(*stream).attached_pic;
+                let codec = (*stream).codec;
+                if packet.data.is_null() || packet.size <= 0 || codec.is_null() {
+                    continue
+                }
+                let codec = ffi::EitherAVCodecContext::from_ptr(codec);
+
+                pictures.push(AttachedPicture {
+                    stream_index: i as usize,
+                    codec_id: *ffmpeg_ffi_avcodeccontext_field!(codec, codec_id),
+                    data: from_raw_parts(packet.data as *const u8, packet.size as usize).to_vec(),
+                });
+            }
+        }
+        pictures
+    }
+
     pub fn find_stream(&self, kind: c_int) -> Option<usize> {
         unsafe {
             let context = &*self.context;
@@ -190,7 +2038,9 @@ impl<T> AvFormatContext<T> {
         }
     }
 
-    pub fn open_stream(&self, index: usize, options: AvDictionary) -> (Result<AvCodecContext, c_int>, AvDictionary) {
+    /// Opens the decoder for stream `index`. The context belongs to the stream, so it is borrowed
+    /// from `self`.
+    pub fn open_stream<'a>(&'a self, index: usize, options: AvDictionary) -> (Result<AvCodecContext<'a>, c_int>, AvDictionary) {
         unsafe {
             let context = &*self.context;
             assert!((index as c_uint) < context.nb_streams);
@@ -215,6 +2065,216 @@ impl<T> AvFormatContext<T> {
         }
     }
 
+    /// Opens a decoder for stream `index` on a copy of the stream's codec context. The decoder
+    /// owns its context rather than borrowing `self`, so it can be kept alongside the format
+    /// context, or outlive it.
+    pub fn open_stream_copy(&self, index: usize, options: AvDictionary) -> (Result<AvCodecContext<'static>, c_int>, AvDictionary) {
+        unsafe {
+            let context = &*self.context;
+            assert!((index as c_uint) < context.nb_streams);
+
+            let stream = *context.streams.offset(index as isize);
+            if stream.is_null() || (*stream).codec.is_null() { return (Err(-1), options) }
+            let codec = ffi::EitherAVCodecContext::from_ptr((*stream).codec);
+
+            let decoder = if let Ok(decoder) = AvCodec::find_decoder(*ffmpeg_ffi_avcodeccontext_field!(codec, codec_id)) {
+                decoder
+            } else {
+                return (Err(-1), options)
+            };
+
+            let copy = AvCodecContext::new(&decoder);
+            let result = ffi::avcodec_copy_context(copy.context.ptr(), codec.ptr());
+            if result < 0 {
+                return (Err(result), options)
+            }
+            let (r, o) = copy.open(&decoder, options);
+            (r.map(|_| copy).or_else(|_| Err(-1)), o)
+        }
+    }
+
+    /// Opens the decoder for stream `index` with `decoder_options` applied on top of `options`.
+    pub fn open_stream_with<'a>(&'a self, index: usize, decoder_options: &DecoderOptions, mut options: AvDictionary)
+                                -> (Result<AvCodecContext<'a>, c_int>, AvDictionary) {
+        decoder_options.apply(&mut options);
+        self.open_stream(index, options)
+    }
+
+    /// Adds a subtitle stream encoded by `encoder` and returns its index together with the opened
+    /// encoder context. Text subtitle encoders are given `header`, or `DEFAULT_ASS_HEADER`.
+    pub fn add_subtitle_stream<'a>(&'a self, encoder: &AvCodec, header: Option<&str>) -> Result<(usize, AvCodecContext<'a>), c_int> {
+        unsafe {
+            let stream = ffi::avformat_new_stream(self.context, encoder.codec);
+            if stream.is_null() {
+                return Err(ffi::AVERROR_ENOMEM)
+            }
+
+            // Subtitles are timed in milliseconds throughout; the muxer may still pick its own
+            // stream time base in `write_header`.
+            let milliseconds = ffi::AVRational { num: 1, den: 1000 };
+            (*stream).time_base = milliseconds;
+
+            let codec = ffi::EitherAVCodecContext::from_ptr((*stream).codec);
+            *ffmpeg_ffi_avcodeccontext_field!(codec, mut codec_type) = ffi::AVMEDIA_TYPE_SUBTITLE;
+            *ffmpeg_ffi_avcodeccontext_field!(codec, mut codec_id) = encoder.id();
+            *ffmpeg_ffi_avcodeccontext_field!(codec, mut time_base) = milliseconds;
+            if (*(*self.context).oformat).flags & ffi::AVFMT_GLOBALHEADER != 0 {
+                *ffmpeg_ffi_avcodeccontext_field!(codec, mut flags) |= ffi::CODEC_FLAG_GLOBAL_HEADER;
+            }
+
+            let codec = AvCodecContext::from_raw(codec.ptr());
+            let descriptor = ffi::avcodec_descriptor_get(encoder.id());
+            if !descriptor.is_null() && (*descriptor).props & ffi::AV_CODEC_PROP_TEXT_SUB != 0 {
+                try!(codec.set_subtitle_header(header.unwrap_or(DEFAULT_ASS_HEADER)));
+            }
+            let (result, _) = codec.open(encoder, AvDictionary::new());
+            try!(result.or_else(|_| Err(-1)));
+
+            Ok(((*stream).index as usize, codec))
+        }
+    }
+
+    /// Adds a chapter to be written by the muxer. This must be done before writing the header,
+    /// and fails with `AVERROR(EINVAL)` on input contexts.
+    pub fn add_chapter(&mut self, id: c_int, time_base: ffi::AVRational, start: i64, end: i64, title: Option<&str>) -> Result<(), c_int> {
+        if unsafe { (*self.context).oformat.is_null() } {
+            return Err(-EINVAL)
+        }
+        let title = match title.map(|title| CString::new(title)) {
+            Some(Ok(title)) => Some(title),
+            Some(Err(_)) => return Err(ERROR_CSTRING),
+            None => None,
+        };
+
+        unsafe {
+            let chapter = ffi::av_mallocz(size_of::<ffi::AVChapter>() as size_t) as *mut ffi::AVChapter;
+            if chapter.is_null() {
+                return Err(ffi::AVERROR_ENOMEM)
+            }
+            (*chapter).id = id;
+            (*chapter).time_base = time_base;
+            (*chapter).start = start;
+            (*chapter).end = end;
+            if let Some(title) = title {
+                let key = CString::new("title").unwrap();
+                let result = ffi::av_dict_set(&mut (*chapter).metadata, key.as_ptr(), title.as_ptr(), 0);
+                if result < 0 {
+                    ffi::av_dict_free(&mut (*chapter).metadata);
+                    ffi::av_free(chapter as *mut c_void);
+                    return Err(result)
+                }
+            }
+
+            // Ownership passes to the context, which frees its chapters and their metadata.
+            // `av_dynarray_add` reports no error; on failure it empties the array instead of
+            // growing it.
+            let context = &mut *self.context;
+            let nb_chapters = context.nb_chapters;
+            ffi::av_dynarray_add(&mut context.chapters as *mut _ as *mut c_void,
+                                 &mut context.nb_chapters as *mut c_uint as *mut c_int,
+                                 chapter as *mut c_void);
+            if context.nb_chapters != nb_chapters + 1 {
+                ffi::av_dict_free(&mut (*chapter).metadata);
+                ffi::av_free(chapter as *mut c_void);
+                return Err(ffi::AVERROR_ENOMEM)
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write_header(&self, options: AvDictionary) -> (Result<(), c_int>, AvDictionary) {
+        unsafe {
+            let mut options_not_found = options.dictionary;
+            let result = ffi::avformat_write_header(self.context, &mut options_not_found);
+
+            forget(options);
+            let options_not_found = AvDictionary {
+                dictionary: options_not_found,
+            };
+
+            if result >= 0 {
+                (Ok(()), options_not_found)
+            } else {
+                (Err(result), options_not_found)
+            }
+        }
+    }
+
+    /// Writes a packet whose timestamps are in its stream's time base, interleaving as needed.
+    pub fn write_packet(&self, packet: &mut AvPacket) -> Result<(), c_int> {
+        unsafe {
+            let result = ffi::av_interleaved_write_frame(self.context, packet.packet.ptr());
+            if result >= 0 {
+                Ok(())
+            } else {
+                Err(result)
+            }
+        }
+    }
+
+    /// Encodes `subtitle` with the encoder returned by `add_subtitle_stream` and writes it.
+    ///
+    /// Like `ffmpeg`, this fails with `AVERROR(EINVAL)` for subtitles without a `pts`. A subtitle
+    /// that ends before it starts, such as one shown until the next replaces it, has no duration.
+    pub fn write_subtitle(&self, stream_index: usize, encoder: &AvCodecContext, subtitle: &AvSubtitle) -> Result<(), c_int> {
+        if subtitle.pts() == ffi::AV_NOPTS_VALUE {
+            return Err(-EINVAL)
+        }
+
+        let mut buf = vec![0u8; SUBTITLE_BUFFER_SIZE + ffi::FF_INPUT_BUFFER_PADDING_SIZE];
+        let size = try!(encoder.encode_subtitle(subtitle, &mut buf[..SUBTITLE_BUFFER_SIZE]));
+        buf.truncate(size + ffi::FF_INPUT_BUFFER_PADDING_SIZE);
+
+        let time_base = self.stream_time_base(stream_index);
+        let av_time_base_q = ffi::AVRational { num: 1, den: ffi::AV_TIME_BASE };
+        let milliseconds = ffi::AVRational { num: 1, den: 1000 };
+
+        let mut packet = AvPacket::new(&mut buf);
+        packet.set_stream_index(stream_index);
+        unsafe {
+            let pts = ffi::av_rescale_q(subtitle.pts(), av_time_base_q, time_base) +
+                      ffi::av_rescale_q(subtitle.start_display_time() as i64, milliseconds, time_base);
+            packet.set_pts(pts);
+            packet.set_dts(pts);
+            packet.set_duration(ffi::av_rescale_q(subtitle.end_display_time().saturating_sub(subtitle.start_display_time()) as i64,
+                                                  milliseconds,
+                                                  time_base) as c_int);
+        }
+        self.write_packet(&mut packet)
+    }
+
+    pub fn write_trailer(&self) -> Result<(), c_int> {
+        unsafe {
+            let result = ffi::av_write_trailer(self.context);
+            if result >= 0 {
+                Ok(())
+            } else {
+                Err(result)
+            }
+        }
+    }
+
+    /// Creates a bitstream filter for the codec of stream `index`, e.g. `h264_mp4toannexb` when
+    /// remuxing MP4 into MPEG-TS.
+    pub fn stream_bitstream_filter<'a>(&'a self, index: usize, name: &str) -> Result<AvBitstreamFilter<'a>, c_int> {
+        unsafe {
+            let context = &*self.context;
+            assert!((index as c_uint) < context.nb_streams);
+
+            let stream = *context.streams.offset(index as isize);
+            if stream.is_null() || (*stream).codec.is_null() { return Err(-1) }
+
+            // The stream's codec context lives as long as `self`.
+            AvBitstreamFilter::from_raw_codec(name, (*stream).codec)
+        }
+    }
+
+    /// Closes the format context and returns the I/O context it was opened with, if any. Call
+    /// `write_trailer` first when muxing.
+    pub fn into_io(mut self) -> Option<Box<T>> {
+        self._avio.take()
+    }
+
     pub fn read_packet(&self, packet: &mut AvPacket) -> Result<(), c_int> {
         unsafe {
             let result = ffi::av_read_frame(self.context, packet.packet.ptr());
@@ -227,6 +2287,12 @@ impl<T> AvFormatContext<T> {
     }
 }
 
+impl<T> Options for AvFormatContext<T> {
+    fn as_options_ptr(&self) -> *mut c_void {
+        self.context as *mut c_void
+    }
+}
+
 #[unsafe_destructor]
 impl<T> Drop for AvFormatContext<T> {
     fn drop(&mut self) {
@@ -7,12 +7,16 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use libc::{c_int, c_void};
+use libc::{c_char, c_double, c_int, c_void};
 use std::any::Any;
-use std::ffi::CString;
+use std::ffi::{CStr, CString};
+use std::i32;
+use std::marker::PhantomData;
 use std::mem;
+use std::num::from_i32;
 use std::ptr;
 use std::slice;
+use avcodec::ERROR_CSTRING;
 use ffi;
 
 pub struct AvFrame {
@@ -27,6 +31,11 @@ impl Drop for AvFrame {
     }
 }
 
+// NB: This only holds for refcounted frames (see `is_refcounted`), whose buffers `libavutil`
+// refcounts with atomics. Without `refcounted_frames` set on the decoder, a frame points into
+// buffers the decoder reuses or frees, and must stay on the decoder's thread.
+unsafe impl Send for AvFrame {}
+
 impl AvFrame {
     pub fn new() -> AvFrame {
         unsafe {
@@ -68,20 +77,28 @@ impl AvFrame {
         }
     }
 
-    pub fn user_data<'a>(&'a self) -> &'a Any {
+    pub fn user_data<'a>(&'a self) -> &'a (Any + Send) {
         unsafe {
             assert!(!(*self.frame).opaque.is_null());
-            let user_data = mem::transmute::<_,&Box<Box<Any>>>(&(*self.frame).opaque);
+            let user_data = mem::transmute::<_,&Box<Box<Any + Send>>>(&(*self.frame).opaque);
             &***user_data
         }
     }
 
-    pub fn set_user_data(&self, user_data: Box<Any>) {
+    /// NB: The data must be `Send`, as it travels with the frame.
+    pub fn set_user_data(&mut self, user_data: Box<Any + Send>) {
         unsafe {
             if !(*self.frame).opaque.is_null() {
-                drop(mem::transmute::<_,Box<Box<Any>>>((*self.frame).opaque));
+                drop(mem::transmute::<_,Box<Box<Any + Send>>>((*self.frame).opaque));
             }
-            (*self.frame).opaque = mem::transmute::<Box<Box<Any>>,*mut c_void>(Box::new(user_data))
+            (*self.frame).opaque = mem::transmute::<Box<Box<Any + Send>>,*mut c_void>(Box::new(user_data))
+        }
+    }
+
+    /// Whether the frame owns references to its buffers, rather than pointing into a decoder's.
+    pub fn is_refcounted(&self) -> bool {
+        unsafe {
+            !(*self.frame).buf[0].is_null()
         }
     }
 
@@ -103,6 +120,108 @@ impl AvFrame {
         }
     }
 
+    /// The side data attached by the decoder, such as closed captions or HDR metadata.
+    pub fn side_data(&self) -> Vec<SideData> {
+        let mut side_data = Vec::new();
+        unsafe {
+            for i in 0..(*self.frame).nb_side_data {
+                let entry = &**(*self.frame).side_data.offset(i as isize);
+                side_data.push(SideData::from_frame_raw(entry.type_, entry.data, entry.size));
+            }
+        }
+        side_data
+    }
+
+    pub fn sample_aspect_ratio(&self) -> ffi::AVRational {
+        unsafe {
+            (*self.frame).sample_aspect_ratio
+        }
+    }
+
+    pub fn is_interlaced(&self) -> bool {
+        unsafe {
+            (*self.frame).interlaced_frame != 0
+        }
+    }
+
+    /// Only meaningful for interlaced frames.
+    pub fn is_top_field_first(&self) -> bool {
+        unsafe {
+            (*self.frame).top_field_first != 0
+        }
+    }
+
+    pub fn is_key_frame(&self) -> bool {
+        unsafe {
+            (*self.frame).keyframe != 0
+        }
+    }
+
+    pub fn picture_type(&self) -> Option<PictureType> {
+        unsafe {
+            PictureType::from_raw((*self.frame).pict_type)
+        }
+    }
+
+    // The fields from here on moved around between versions of `libavutil`, so they are read
+    // through its accessor functions rather than the struct.
+
+    /// The frame's presentation time as best the decoder can guess it, which unlike `pts` is
+    /// nearly always set.
+    pub fn best_effort_timestamp(&self) -> i64 {
+        unsafe {
+            ffi::av_frame_get_best_effort_timestamp(self.frame)
+        }
+    }
+
+    /// The duration of the packet the frame was decoded from, in the stream's time base, or 0 if
+    /// unknown.
+    pub fn pkt_duration(&self) -> i64 {
+        unsafe {
+            ffi::av_frame_get_pkt_duration(self.frame)
+        }
+    }
+
+    /// The byte offset in the input of the packet the frame was decoded from, or -1 if unknown.
+    pub fn pkt_pos(&self) -> i64 {
+        unsafe {
+            ffi::av_frame_get_pkt_pos(self.frame)
+        }
+    }
+
+    pub fn metadata(&self) -> AvDictionary {
+        unsafe {
+            AvDictionary::from_raw(ffi::av_frame_get_metadata(self.frame))
+        }
+    }
+
+    /// A combination of the `FF_DECODE_ERROR_*` flags.
+    pub fn decode_error_flags(&self) -> c_int {
+        unsafe {
+            ffi::av_frame_get_decode_error_flags(self.frame)
+        }
+    }
+
+    /// Whether the decoder flagged the frame as corrupt or hit errors decoding it, e.g. because
+    /// a reference frame was missing after a seek.
+    pub fn is_corrupt(&self) -> bool {
+        unsafe {
+            (*self.frame).flags & ffi::AV_FRAME_FLAG_CORRUPT != 0 || self.decode_error_flags() != 0
+        }
+    }
+
+    pub fn color_space(&self) -> Option<ColorSpace> {
+        unsafe {
+            ColorSpace::from_raw(ffi::av_frame_get_colorspace(self.frame))
+        }
+    }
+
+    pub fn color_range(&self) -> Option<ColorRange> {
+        unsafe {
+            ColorRange::from_raw(ffi::av_frame_get_color_range(self.frame))
+        }
+    }
+
     pub fn video_data<'a>(&'a self, plane_index: usize) -> &'a [u8] {
         let len = self.linesize(plane_index) * self.height();
         unsafe {
@@ -122,6 +241,274 @@ impl AvFrame {
     }
 }
 
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum PictureType {
+    None = 0,
+    I = 1,
+    P = 2,
+    B = 3,
+    S = 4,
+    SI = 5,
+    SP = 6,
+    BI = 7,
+}
+
+impl PictureType {
+    pub fn as_raw(&self) -> ffi::AVPictureType {
+        *self as ffi::AVPictureType
+    }
+
+    pub fn from_raw(v: ffi::AVPictureType) -> Option<Self> {
+        from_i32(v)
+    }
+}
+
+/// The chromaticity coordinates of the source primaries (ISO/IEC 23001-8 `ColourPrimaries`).
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum ColorPrimaries {
+    Bt709 = 1,
+    Unspecified = 2,
+    Bt470M = 4,
+    Bt470Bg = 5,
+    Smpte170M = 6,
+    Smpte240M = 7,
+    Film = 8,
+    Bt2020 = 9,
+    Smpte428 = 10,
+    Smpte431 = 11,
+    Smpte432 = 12,
+    JedecP22 = 22,
+}
+
+impl ColorPrimaries {
+    pub fn as_raw(&self) -> ffi::AVColorPrimaries {
+        *self as ffi::AVColorPrimaries
+    }
+
+    pub fn from_raw(v: ffi::AVColorPrimaries) -> Option<Self> {
+        from_i32(v)
+    }
+}
+
+/// The transfer function, i.e. gamma curve (ISO/IEC 23001-8 `TransferCharacteristics`).
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum ColorTransferCharacteristic {
+    Bt709 = 1,
+    Unspecified = 2,
+    Gamma22 = 4,
+    Gamma28 = 5,
+    Smpte170M = 6,
+    Smpte240M = 7,
+    Linear = 8,
+    Log = 9,
+    LogSqrt = 10,
+    Iec61966_2_4 = 11,
+    Bt1361Ecg = 12,
+    Iec61966_2_1 = 13,
+    Bt2020_10 = 14,
+    Bt2020_12 = 15,
+    /// The PQ curve used by HDR10.
+    Smpte2084 = 16,
+    Smpte428 = 17,
+    /// Hybrid log-gamma.
+    AribStdB67 = 18,
+}
+
+impl ColorTransferCharacteristic {
+    pub fn as_raw(&self) -> ffi::AVColorTransferCharacteristic {
+        *self as ffi::AVColorTransferCharacteristic
+    }
+
+    pub fn from_raw(v: ffi::AVColorTransferCharacteristic) -> Option<Self> {
+        from_i32(v)
+    }
+}
+
+/// The YUV/RGB conversion matrix (ISO/IEC 23001-8 `MatrixCoefficients`).
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum ColorSpace {
+    Rgb = 0,
+    Bt709 = 1,
+    Unspecified = 2,
+    Fcc = 4,
+    Bt470Bg = 5,
+    Smpte170M = 6,
+    Smpte240M = 7,
+    YCgCo = 8,
+    Bt2020Ncl = 9,
+    Bt2020Cl = 10,
+    Smpte2085 = 11,
+}
+
+impl ColorSpace {
+    pub fn as_raw(&self) -> ffi::AVColorSpace {
+        *self as ffi::AVColorSpace
+    }
+
+    pub fn from_raw(v: ffi::AVColorSpace) -> Option<Self> {
+        from_i32(v)
+    }
+}
+
+#[derive(Debug, Copy, Clone, FromPrimitive, PartialEq, Eq)]
+pub enum ColorRange {
+    Unspecified = 0,
+    /// Limited ("TV") range, e.g. 16-235 for 8-bit luma.
+    Mpeg = 1,
+    /// Full ("PC") range.
+    Jpeg = 2,
+}
+
+impl ColorRange {
+    pub fn as_raw(&self) -> ffi::AVColorRange {
+        *self as ffi::AVColorRange
+    }
+
+    pub fn from_raw(v: ffi::AVColorRange) -> Option<Self> {
+        from_i32(v)
+    }
+}
+
+/// A 3x3 transformation matrix to apply to a video before display, as stored by phones that
+/// record in portrait orientation.
+#[derive(Copy)]
+pub struct DisplayMatrix {
+    pub matrix: [i32; 9],
+}
+
+impl DisplayMatrix {
+    /// The counterclockwise rotation in degrees, between -180 and 180. Rotate the picture by the
+    /// negation of this to display it upright.
+    pub fn rotation(&self) -> c_double {
+        unsafe {
+            ffi::av_display_rotation_get(self.matrix.as_ptr())
+        }
+    }
+}
+
+/// Side data attached to a frame, packet or stream.
+///
+/// Anything of a type not decoded here, or too short for the type it claims to be, is kept as
+/// `OtherFrameData` or `OtherPacketData`.
+pub enum SideData {
+    DisplayMatrix(DisplayMatrix),
+    Stereo3D(ffi::AVStereo3D),
+    MasteringDisplay(ffi::AVMasteringDisplayMetadata),
+    ContentLightLevel(ffi::AVContentLightMetadata),
+    ReplayGain(ffi::AVReplayGain),
+    /// Closed captions as carried in ATSC A/53 user data.
+    A53ClosedCaptions(Vec<u8>),
+    /// The number of audio samples to drop from the start and end of the decoded output.
+    SkipSamples {
+        start: u32,
+        end: u32,
+    },
+    MotionVectors(Vec<ffi::AVMotionVector>),
+    /// Codec extradata that changed mid-stream.
+    NewExtradata(Vec<u8>),
+    OtherFrameData(ffi::AVFrameSideDataType, Vec<u8>),
+    OtherPacketData(ffi::AVPacketSideDataType, Vec<u8>),
+}
+
+impl SideData {
+    pub unsafe fn from_frame_raw(kind: ffi::AVFrameSideDataType, data: *const u8, size: c_int)
+                                 -> SideData {
+        let bytes = side_data_bytes(data, size);
+        let side_data = match kind {
+            ffi::AV_FRAME_DATA_DISPLAYMATRIX => SideData::display_matrix(bytes),
+            ffi::AV_FRAME_DATA_STEREO3D => read_side_data(bytes).map(SideData::Stereo3D),
+            ffi::AV_FRAME_DATA_MASTERING_DISPLAY_METADATA => {
+                read_side_data(bytes).map(SideData::MasteringDisplay)
+            }
+            ffi::AV_FRAME_DATA_CONTENT_LIGHT_LEVEL => {
+                read_side_data(bytes).map(SideData::ContentLightLevel)
+            }
+            ffi::AV_FRAME_DATA_REPLAYGAIN => read_side_data(bytes).map(SideData::ReplayGain),
+            ffi::AV_FRAME_DATA_A53_CC => Some(SideData::A53ClosedCaptions(bytes.to_vec())),
+            ffi::AV_FRAME_DATA_SKIP_SAMPLES => SideData::skip_samples(bytes),
+            ffi::AV_FRAME_DATA_MOTION_VECTORS => {
+                let count = bytes.len() / mem::size_of::<ffi::AVMotionVector>();
+                if count == 0 {
+                    Some(SideData::MotionVectors(Vec::new()))
+                } else {
+                    let vectors = slice::from_raw_parts(data as *const ffi::AVMotionVector, count);
+                    Some(SideData::MotionVectors(vectors.to_vec()))
+                }
+            }
+            _ => None,
+        };
+        side_data.unwrap_or_else(|| SideData::OtherFrameData(kind, bytes.to_vec()))
+    }
+
+    pub unsafe fn from_packet_raw(kind: ffi::AVPacketSideDataType, data: *const u8, size: c_int)
+                                  -> SideData {
+        let bytes = side_data_bytes(data, size);
+        let side_data = match kind {
+            ffi::AV_PKT_DATA_DISPLAYMATRIX => SideData::display_matrix(bytes),
+            ffi::AV_PKT_DATA_STEREO3D => read_side_data(bytes).map(SideData::Stereo3D),
+            ffi::AV_PKT_DATA_REPLAYGAIN => read_side_data(bytes).map(SideData::ReplayGain),
+            ffi::AV_PKT_DATA_SKIP_SAMPLES => SideData::skip_samples(bytes),
+            ffi::AV_PKT_DATA_NEW_EXTRADATA => Some(SideData::NewExtradata(bytes.to_vec())),
+            _ => None,
+        };
+        side_data.unwrap_or_else(|| SideData::OtherPacketData(kind, bytes.to_vec()))
+    }
+
+    /// Decodes the side data arrays of packets and streams.
+    pub unsafe fn from_packet_list(list: *const ffi::AVPacketSideData, count: c_int)
+                                   -> Vec<SideData> {
+        let mut side_data = Vec::new();
+        if list.is_null() {
+            return side_data
+        }
+        for i in 0..count {
+            let entry = &*list.offset(i as isize);
+            side_data.push(SideData::from_packet_raw(entry.type_, entry.data, entry.size));
+        }
+        side_data
+    }
+
+    unsafe fn display_matrix(bytes: &[u8]) -> Option<SideData> {
+        read_side_data(bytes).map(|matrix| SideData::DisplayMatrix(DisplayMatrix {
+            matrix: matrix,
+        }))
+    }
+
+    /// Stored as two little-endian 32-bit sample counts, followed by two reason bytes.
+    fn skip_samples(bytes: &[u8]) -> Option<SideData> {
+        if bytes.len() < 8 {
+            return None
+        }
+        let read_u32 = |offset: usize| {
+            (bytes[offset] as u32) |
+            ((bytes[offset + 1] as u32) << 8) |
+            ((bytes[offset + 2] as u32) << 16) |
+            ((bytes[offset + 3] as u32) << 24)
+        };
+        Some(SideData::SkipSamples {
+            start: read_u32(0),
+            end: read_u32(4),
+        })
+    }
+}
+
+unsafe fn side_data_bytes<'a>(data: *const u8, size: c_int) -> &'a [u8] {
+    if data.is_null() || size <= 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(data, size as usize)
+    }
+}
+
+/// Copies a struct out of side data, which FFmpeg allocates suitably aligned.
+unsafe fn read_side_data<T: Copy>(bytes: &[u8]) -> Option<T> {
+    if bytes.len() < mem::size_of::<T>() {
+        None
+    } else {
+        Some(ptr::read(bytes.as_ptr() as *const T))
+    }
+}
+
 pub struct AvDictionary {
     pub dictionary: *mut ffi::AVDictionary,
 }
@@ -134,6 +521,8 @@ impl Drop for AvDictionary {
     }
 }
 
+unsafe impl Send for AvDictionary {}
+
 impl AvDictionary {
     pub fn new() -> AvDictionary {
         AvDictionary {
@@ -141,6 +530,15 @@ impl AvDictionary {
         }
     }
 
+    /// Copies a dictionary owned by FFmpeg, such as a stream's or chapter's metadata.
+    pub unsafe fn from_raw(dictionary: *const ffi::AVDictionary) -> AvDictionary {
+        let mut copy = AvDictionary::new();
+        if !dictionary.is_null() {
+            ffi::av_dict_copy(&mut copy.dictionary, dictionary, 0);
+        }
+        copy
+    }
+
     pub fn set(&mut self, key: &str, value: &str) {
         unsafe {
             let key = CString::new(key.as_bytes()).unwrap();
@@ -148,6 +546,365 @@ impl AvDictionary {
             assert!(ffi::av_dict_set(&mut self.dictionary, key.as_ptr(), value.as_ptr(), 0) >= 0);
         }
     }
+
+    /// Looks up `key`, ignoring case as FFmpeg does by default.
+    pub fn get(&self, key: &str) -> Option<String> {
+        let key = match CString::new(key.as_bytes()) {
+            Ok(key) => key,
+            Err(_) => return None,
+        };
+        unsafe {
+            let entry = ffi::av_dict_get(self.dictionary, key.as_ptr(), ptr::null(), 0);
+            if entry.is_null() {
+                None
+            } else {
+                from_cstr((*entry).value)
+            }
+        }
+    }
+
+    pub fn entries(&self) -> Vec<(String, String)> {
+        let mut entries = Vec::new();
+        unsafe {
+            let empty = CString::new("").unwrap();
+            let mut entry = ptr::null_mut();
+            loop {
+                entry = ffi::av_dict_get(self.dictionary, empty.as_ptr(), entry, ffi::AV_DICT_IGNORE_SUFFIX);
+                if entry.is_null() {
+                    break
+                }
+                entries.push((from_cstr((*entry).key).unwrap_or(String::new()),
+                              from_cstr((*entry).value).unwrap_or(String::new())));
+            }
+        }
+        entries
+    }
+}
+
+/// An FFmpeg object that begins with an `AVClass` pointer and so can be configured through the
+/// `av_opt_*` API.
+///
+/// `search_children` corresponds to `AV_OPT_SEARCH_CHILDREN`, which also looks the option up in
+/// the object's children (e.g. the private codec or demuxer context).
+pub trait Options {
+    fn as_options_ptr(&self) -> *mut c_void;
+
+    fn opt_set(&self, name: &str, value: &str, search_children: bool) -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        let value = try!(CString::new(value).or_else(|_| Err(ERROR_CSTRING)));
+        opt_result(unsafe {
+            ffi::av_opt_set(self.as_options_ptr(), name.as_ptr(), value.as_ptr(), search_flags(search_children))
+        })
+    }
+
+    fn opt_set_int(&self, name: &str, value: i64, search_children: bool) -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        opt_result(unsafe {
+            ffi::av_opt_set_int(self.as_options_ptr(), name.as_ptr(), value, search_flags(search_children))
+        })
+    }
+
+    fn opt_set_double(&self, name: &str, value: c_double, search_children: bool) -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        opt_result(unsafe {
+            ffi::av_opt_set_double(self.as_options_ptr(), name.as_ptr(), value, search_flags(search_children))
+        })
+    }
+
+    fn opt_set_q(&self, name: &str, value: ffi::AVRational, search_children: bool) -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        opt_result(unsafe {
+            ffi::av_opt_set_q(self.as_options_ptr(), name.as_ptr(), value, search_flags(search_children))
+        })
+    }
+
+    fn opt_set_bin(&self, name: &str, value: &[u8], search_children: bool) -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        opt_result(unsafe {
+            ffi::av_opt_set_bin(self.as_options_ptr(),
+                                name.as_ptr(),
+                                value.as_ptr(),
+                                value.len() as c_int,
+                                search_flags(search_children))
+        })
+    }
+
+    fn opt_set_image_size(&self, name: &str, width: c_int, height: c_int, search_children: bool)
+                          -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        opt_result(unsafe {
+            ffi::av_opt_set_image_size(self.as_options_ptr(), name.as_ptr(), width, height, search_flags(search_children))
+        })
+    }
+
+    fn opt_set_pixel_fmt(&self, name: &str, format: c_int, search_children: bool) -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        opt_result(unsafe {
+            ffi::av_opt_set_pixel_fmt(self.as_options_ptr(), name.as_ptr(), format, search_flags(search_children))
+        })
+    }
+
+    fn opt_set_sample_fmt(&self, name: &str, format: ffi::AVSampleFormat, search_children: bool)
+                          -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        opt_result(unsafe {
+            ffi::av_opt_set_sample_fmt(self.as_options_ptr(), name.as_ptr(), format, search_flags(search_children))
+        })
+    }
+
+    fn opt_set_channel_layout(&self, name: &str, layout: u64, search_children: bool) -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        opt_result(unsafe {
+            ffi::av_opt_set_channel_layout(self.as_options_ptr(),
+                                           name.as_ptr(),
+                                           layout as i64,
+                                           search_flags(search_children))
+        })
+    }
+
+    fn opt_set_dict(&self, name: &str, value: &AvDictionary, search_children: bool) -> Result<(),c_int> {
+        let name = try!(opt_name(name));
+        opt_result(unsafe {
+            ffi::av_opt_set_dict_val(self.as_options_ptr(), name.as_ptr(), value.dictionary, search_flags(search_children))
+        })
+    }
+
+    /// Returns any option formatted as a string, the same way the `ffmpeg` tool prints it.
+    fn opt_get(&self, name: &str, search_children: bool) -> Result<String,c_int> {
+        let name = try!(opt_name(name));
+        let mut out_val = ptr::null_mut();
+        let result = unsafe {
+            ffi::av_opt_get(self.as_options_ptr(), name.as_ptr(), search_flags(search_children), &mut out_val)
+        };
+        if result < 0 {
+            return Err(result)
+        }
+        unsafe {
+            let value = from_cstr(out_val as *const c_char).unwrap_or(String::new());
+            ffi::av_free(out_val as *mut c_void);
+            Ok(value)
+        }
+    }
+
+    fn opt_get_int(&self, name: &str, search_children: bool) -> Result<i64,c_int> {
+        let name = try!(opt_name(name));
+        let mut out_val = 0;
+        let result = unsafe {
+            ffi::av_opt_get_int(self.as_options_ptr(), name.as_ptr(), search_flags(search_children), &mut out_val)
+        };
+        opt_result(result).map(|_| out_val)
+    }
+
+    fn opt_get_double(&self, name: &str, search_children: bool) -> Result<c_double,c_int> {
+        let name = try!(opt_name(name));
+        let mut out_val = 0.0;
+        let result = unsafe {
+            ffi::av_opt_get_double(self.as_options_ptr(), name.as_ptr(), search_flags(search_children), &mut out_val)
+        };
+        opt_result(result).map(|_| out_val)
+    }
+
+    fn opt_get_q(&self, name: &str, search_children: bool) -> Result<ffi::AVRational,c_int> {
+        let name = try!(opt_name(name));
+        let mut out_val = ffi::AVRational {
+            num: 0,
+            den: 0,
+        };
+        let result = unsafe {
+            ffi::av_opt_get_q(self.as_options_ptr(), name.as_ptr(), search_flags(search_children), &mut out_val)
+        };
+        opt_result(result).map(|_| out_val)
+    }
+
+    /// FFmpeg only exposes binary options as hex strings, so this decodes `opt_get`'s output.
+    fn opt_get_bin(&self, name: &str, search_children: bool) -> Result<Vec<u8>,c_int> {
+        let hex = try!(self.opt_get(name, search_children));
+        let hex = hex.as_bytes();
+        if hex.len() % 2 != 0 {
+            return Err(ffi::AVERROR_INVALIDDATA)
+        }
+        let digit = |c: u8| (c as char).to_digit(16).map(|d| d as u8).ok_or(ffi::AVERROR_INVALIDDATA);
+        let mut value = Vec::with_capacity(hex.len() / 2);
+        for pair in hex.chunks(2) {
+            value.push((try!(digit(pair[0])) << 4) | try!(digit(pair[1])));
+        }
+        Ok(value)
+    }
+
+    fn opt_get_image_size(&self, name: &str, search_children: bool) -> Result<(c_int, c_int),c_int> {
+        let name = try!(opt_name(name));
+        let (mut width, mut height) = (0, 0);
+        let result = unsafe {
+            ffi::av_opt_get_image_size(self.as_options_ptr(),
+                                       name.as_ptr(),
+                                       search_flags(search_children),
+                                       &mut width,
+                                       &mut height)
+        };
+        opt_result(result).map(|_| (width, height))
+    }
+
+    fn opt_get_pixel_fmt(&self, name: &str, search_children: bool) -> Result<c_int,c_int> {
+        let name = try!(opt_name(name));
+        let mut out_val = 0;
+        let result = unsafe {
+            ffi::av_opt_get_pixel_fmt(self.as_options_ptr(), name.as_ptr(), search_flags(search_children), &mut out_val)
+        };
+        opt_result(result).map(|_| out_val)
+    }
+
+    fn opt_get_sample_fmt(&self, name: &str, search_children: bool) -> Result<ffi::AVSampleFormat,c_int> {
+        let name = try!(opt_name(name));
+        let mut out_val = 0;
+        let result = unsafe {
+            ffi::av_opt_get_sample_fmt(self.as_options_ptr(), name.as_ptr(), search_flags(search_children), &mut out_val)
+        };
+        opt_result(result).map(|_| out_val)
+    }
+
+    fn opt_get_channel_layout(&self, name: &str, search_children: bool) -> Result<u64,c_int> {
+        let name = try!(opt_name(name));
+        let mut out_val = 0;
+        let result = unsafe {
+            ffi::av_opt_get_channel_layout(self.as_options_ptr(),
+                                           name.as_ptr(),
+                                           search_flags(search_children),
+                                           &mut out_val)
+        };
+        opt_result(result).map(|_| out_val as u64)
+    }
+
+    fn opt_get_dict(&self, name: &str, search_children: bool) -> Result<AvDictionary,c_int> {
+        let name = try!(opt_name(name));
+        let mut out_val = ptr::null_mut();
+        let result = unsafe {
+            ffi::av_opt_get_dict_val(self.as_options_ptr(), name.as_ptr(), search_flags(search_children), &mut out_val)
+        };
+        opt_result(result).map(|_| AvDictionary {
+            dictionary: out_val,
+        })
+    }
+
+    /// Iterates over the options declared directly on this object, including named constants.
+    fn opt_iter<'a>(&'a self) -> OptionIter<'a> {
+        OptionIter {
+            object: self.as_options_ptr(),
+            prev: ptr::null(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+fn opt_name(name: &str) -> Result<CString,c_int> {
+    CString::new(name).or_else(|_| Err(ERROR_CSTRING))
+}
+
+fn opt_result(result: c_int) -> Result<(),c_int> {
+    if result >= 0 {
+        Ok(())
+    } else {
+        Err(result)
+    }
+}
+
+fn search_flags(search_children: bool) -> c_int {
+    if search_children {
+        ffi::AV_OPT_SEARCH_CHILDREN
+    } else {
+        0
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum OptionDefault {
+    Int(i64),
+    Double(c_double),
+    String(Option<String>),
+    Rational(ffi::AVRational),
+    None,
+}
+
+#[derive(Debug, Clone)]
+pub struct OptionInfo {
+    pub name: String,
+    pub help: Option<String>,
+    pub kind: ffi::AVOptionType,
+    pub default: OptionDefault,
+    pub min: c_double,
+    pub max: c_double,
+    pub flags: c_int,
+    pub unit: Option<String>,
+}
+
+impl OptionInfo {
+    unsafe fn from_raw(option: &ffi::AVOption) -> OptionInfo {
+        let default = match option.type_ {
+            ffi::AV_OPT_TYPE_FLAGS | ffi::AV_OPT_TYPE_INT | ffi::AV_OPT_TYPE_INT64 |
+            ffi::AV_OPT_TYPE_CONST | ffi::AV_OPT_TYPE_PIXEL_FMT | ffi::AV_OPT_TYPE_SAMPLE_FMT |
+            ffi::AV_OPT_TYPE_DURATION | ffi::AV_OPT_TYPE_CHANNEL_LAYOUT => {
+                OptionDefault::Int(option.default_val)
+            }
+            ffi::AV_OPT_TYPE_DOUBLE | ffi::AV_OPT_TYPE_FLOAT => {
+                OptionDefault::Double(mem::transmute::<i64,c_double>(option.default_val))
+            }
+            ffi::AV_OPT_TYPE_STRING | ffi::AV_OPT_TYPE_IMAGE_SIZE | ffi::AV_OPT_TYPE_VIDEO_RATE |
+            ffi::AV_OPT_TYPE_COLOR => {
+                OptionDefault::String(from_cstr(option.default_val as usize as *const c_char))
+            }
+            ffi::AV_OPT_TYPE_RATIONAL => {
+                // Stored as a double, which `av_opt_set_defaults` converts the same way.
+                let default = mem::transmute::<i64,c_double>(option.default_val);
+                OptionDefault::Rational(ffi::av_d2q(default, i32::MAX))
+            }
+            _ => OptionDefault::None,
+        };
+
+        OptionInfo {
+            name: from_cstr(option.name).unwrap_or(String::new()),
+            help: from_cstr(option.help),
+            kind: option.type_,
+            default: default,
+            min: option.min,
+            max: option.max,
+            flags: option.flags,
+            unit: from_cstr(option.unit),
+        }
+    }
+
+    /// Named constants are the values accepted by the options sharing their `unit`.
+    pub fn is_constant(&self) -> bool {
+        self.kind == ffi::AV_OPT_TYPE_CONST
+    }
+}
+
+pub struct OptionIter<'a> {
+    object: *mut c_void,
+    prev: *const ffi::AVOption,
+    _marker: PhantomData<&'a ()>,
+}
+
+impl<'a> Iterator for OptionIter<'a> {
+    type Item = OptionInfo;
+
+    fn next(&mut self) -> Option<OptionInfo> {
+        unsafe {
+            self.prev = ffi::av_opt_next(self.object, self.prev);
+            if self.prev.is_null() {
+                None
+            } else {
+                Some(OptionInfo::from_raw(&*self.prev))
+            }
+        }
+    }
+}
+
+/// Copies a C string owned by FFmpeg, treating null as absent.
+pub unsafe fn from_cstr(ptr: *const c_char) -> Option<String> {
+    if ptr.is_null() {
+        None
+    } else {
+        Some(String::from_utf8_lossy(CStr::from_ptr(ptr).to_bytes()).into_owned())
+    }
 }
 
 pub mod samples {
@@ -181,3 +938,163 @@ pub mod samples {
         }
     }
 }
+
+pub mod log {
+    use libc::{c_char, c_int, c_void};
+    use std::ffi::CStr;
+    use avutil::from_cstr;
+    use ffi;
+
+    #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+    pub enum Level {
+        Quiet,
+        Panic,
+        Fatal,
+        Error,
+        Warning,
+        Info,
+        Verbose,
+        Debug,
+        /// FFmpeg 2.5 has no level below `AV_LOG_DEBUG`, so this is the same as `Debug`.
+        Trace,
+    }
+
+    impl Level {
+        pub fn as_raw(&self) -> c_int {
+            match *self {
+                Level::Quiet => ffi::AV_LOG_QUIET,
+                Level::Panic => ffi::AV_LOG_PANIC,
+                Level::Fatal => ffi::AV_LOG_FATAL,
+                Level::Error => ffi::AV_LOG_ERROR,
+                Level::Warning => ffi::AV_LOG_WARNING,
+                Level::Info => ffi::AV_LOG_INFO,
+                Level::Verbose => ffi::AV_LOG_VERBOSE,
+                Level::Debug | Level::Trace => ffi::AV_LOG_DEBUG,
+            }
+        }
+
+        /// Maps a raw `AV_LOG_*` value onto the closest level at or above it in severity.
+        pub fn from_raw(level: c_int) -> Level {
+            if level < ffi::AV_LOG_PANIC {
+                Level::Quiet
+            } else if level < ffi::AV_LOG_FATAL {
+                Level::Panic
+            } else if level < ffi::AV_LOG_ERROR {
+                Level::Fatal
+            } else if level < ffi::AV_LOG_WARNING {
+                Level::Error
+            } else if level < ffi::AV_LOG_INFO {
+                Level::Warning
+            } else if level < ffi::AV_LOG_VERBOSE {
+                Level::Info
+            } else if level < ffi::AV_LOG_DEBUG {
+                Level::Verbose
+            } else {
+                Level::Debug
+            }
+        }
+    }
+
+    pub fn set_level(level: Level) {
+        unsafe {
+            ffi::av_log_set_level(level.as_raw())
+        }
+    }
+
+    pub fn level() -> Level {
+        unsafe {
+            Level::from_raw(ffi::av_log_get_level())
+        }
+    }
+
+    /// Restores FFmpeg's default callback, which prints to stderr.
+    pub fn reset_callback() {
+        unsafe {
+            ffi::av_log_set_callback(default_callback)
+        }
+    }
+
+    /// Routes all `libav*` messages through the `log` crate under the `ffmpeg` target.
+    ///
+    /// Messages above the level configured with `set_level` are dropped before formatting.
+    #[cfg(feature = "log")]
+    pub fn install_callback() {
+        unsafe {
+            ffi::av_log_set_callback(log_callback)
+        }
+    }
+
+    extern "C" fn default_callback(avcl: *mut c_void,
+                                   level: c_int,
+                                   fmt: *const c_char,
+                                   vl: ffi::va_list) {
+        unsafe {
+            ffi::av_log_default_callback(avcl, level, fmt, vl)
+        }
+    }
+
+    #[cfg(feature = "log")]
+    extern "C" fn log_callback(avcl: *mut c_void,
+                               level: c_int,
+                               fmt: *const c_char,
+                               vl: ffi::va_list) {
+        use log::LogLevel;
+
+        unsafe {
+            if level > ffi::av_log_get_level() {
+                return
+            }
+
+            let log_level = match Level::from_raw(level) {
+                Level::Quiet => return,
+                Level::Panic | Level::Fatal | Level::Error => LogLevel::Error,
+                Level::Warning => LogLevel::Warn,
+                Level::Info => LogLevel::Info,
+                Level::Verbose | Level::Debug | Level::Trace => LogLevel::Debug,
+            };
+            if !log_enabled!(target: "ffmpeg", log_level) {
+                return
+            }
+
+            // The prefix is left to us so that the context is reported the same way for every
+            // message, rather than only at the start of each line.
+            let mut line = [0 as c_char; 1024];
+            let mut print_prefix = 0;
+            ffi::av_log_format_line(avcl,
+                                    level,
+                                    fmt,
+                                    vl,
+                                    line.as_mut_ptr(),
+                                    line.len() as c_int,
+                                    &mut print_prefix);
+            let message = String::from_utf8_lossy(CStr::from_ptr(line.as_ptr()).to_bytes());
+            let message = message.trim_right();
+            if message.is_empty() {
+                return
+            }
+
+            match item_name(avcl) {
+                Some(name) => log!(target: "ffmpeg", log_level, "[{} @ {:p}] {}", name, avcl, message),
+                None => log!(target: "ffmpeg", log_level, "{}", message),
+            }
+        }
+    }
+
+    /// Looks up the `AVClass` item name of a logging context, as FFmpeg's own prefix does.
+    #[cfg(feature = "log")]
+    unsafe fn item_name(avcl: *mut c_void) -> Option<String> {
+        if avcl.is_null() {
+            return None
+        }
+
+        let class = *(avcl as *const *const ffi::AVClass);
+        if class.is_null() {
+            return None
+        }
+
+        from_cstr(match (*class).item_name {
+            Some(item_name) => item_name(avcl),
+            None => ffi::av_default_item_name(avcl),
+        })
+    }
+}
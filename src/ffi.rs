@@ -9,11 +9,13 @@
 
 pub const FF_INPUT_BUFFER_PADDING_SIZE: usize = 32;
 
-use libc::{c_char, c_double, c_float, c_int, c_short, c_uint, c_void, size_t };
+use libc::{c_char, c_double, c_float, c_int, c_short, c_uint, c_ulong, c_void, size_t };
 
 pub type AVCodecID = c_int;
+pub type AVColorPrimaries = c_int;
 pub type AVColorRange = c_int;
 pub type AVColorSpace = c_int;
+pub type AVColorTransferCharacteristic = c_int;
 pub type AVPictureType = c_int;
 pub type AVStreamParseType = c_int;
 pub type AVSampleFormat = c_int;
@@ -22,6 +24,9 @@ pub type AVDiscard = c_int;
 
 pub const AV_NUM_DATA_POINTERS: usize = 8;
 
+pub const AV_NOPTS_VALUE: i64 = 0x8000000000000000u64 as i64;
+pub const AV_TIME_BASE: c_int = 1000000;
+
 pub const AVFMT_FLAG_GENPTS: c_int          = 0x00001;
 pub const AVFMT_FLAG_IGNIDX: c_int          = 0x00002;
 pub const AVFMT_FLAG_NONBLOCK: c_int        = 0x00004;
@@ -37,18 +42,196 @@ pub const AVFMT_FLAG_SORT_DTS: c_int        = 0x10000;
 pub const AVFMT_FLAG_PRIV_OPT: c_int        = 0x20000;
 pub const AVFMT_FLAG_KEEP_SIDE_DATA: c_int  = 0x40000;
 
+pub const AVPROBE_SCORE_MAX: c_int = 100;
+pub const AVPROBE_PADDING_SIZE: usize = 32;
+
+pub const AV_DISPOSITION_DEFAULT: c_int          = 0x0001;
+pub const AV_DISPOSITION_DUB: c_int              = 0x0002;
+pub const AV_DISPOSITION_ORIGINAL: c_int         = 0x0004;
+pub const AV_DISPOSITION_COMMENT: c_int          = 0x0008;
+pub const AV_DISPOSITION_LYRICS: c_int           = 0x0010;
+pub const AV_DISPOSITION_KARAOKE: c_int          = 0x0020;
+pub const AV_DISPOSITION_FORCED: c_int           = 0x0040;
+pub const AV_DISPOSITION_HEARING_IMPAIRED: c_int = 0x0080;
+pub const AV_DISPOSITION_VISUAL_IMPAIRED: c_int  = 0x0100;
+pub const AV_DISPOSITION_CLEAN_EFFECTS: c_int    = 0x0200;
+pub const AV_DISPOSITION_ATTACHED_PIC: c_int     = 0x0400;
+
+pub const AVFMT_NOFILE: c_int        = 0x0001;
+pub const AVFMT_NEEDNUMBER: c_int    = 0x0002;
+pub const AVFMT_SHOW_IDS: c_int      = 0x0008;
+pub const AVFMT_GLOBALHEADER: c_int  = 0x0040;
+pub const AVFMT_NOTIMESTAMPS: c_int  = 0x0080;
+
+pub const AVIO_FLAG_READ: c_int       = 1;
+pub const AVIO_FLAG_WRITE: c_int      = 2;
+pub const AVIO_FLAG_READ_WRITE: c_int = 3;
+
+/// Passed as `whence` to ask a seek callback for the size of the stream instead of seeking.
+pub const AVSEEK_SIZE: c_int  = 0x10000;
+/// ORed into `whence` when a seek should be done even if it is expensive.
+pub const AVSEEK_FORCE: c_int = 0x20000;
+
+pub const SEEK_SET: c_int = 0;
+pub const SEEK_CUR: c_int = 1;
+pub const SEEK_END: c_int = 2;
+
+pub const CODEC_FLAG_GLOBAL_HEADER: c_int = 0x00400000;
+
 pub const AVMEDIA_TYPE_VIDEO: c_int = 0;
 pub const AVMEDIA_TYPE_AUDIO: c_int = 1;
 pub const AVMEDIA_TYPE_DATA: c_int = 2;
 pub const AVMEDIA_TYPE_SUBTITLE: c_int = 3;
 pub const AVMEDIA_TYPE_ATTACHMENT: c_int = 4;
 
+pub const AVERROR_EAGAIN: c_int = -11;
+pub const AVERROR_ENOMEM: c_int = -12;
+pub const AVERROR_EOF: c_int = -0x20464F45; // FFERRTAG('E','O','F',' ')
+pub const AVERROR_EXIT: c_int = -0x54495845; // FFERRTAG('E','X','I','T')
+pub const AVERROR_INVALIDDATA: c_int = -0x41444E49; // FFERRTAG('I','N','D','A')
+
+pub const AV_LOG_QUIET: c_int = -8;
+pub const AV_LOG_PANIC: c_int = 0;
+pub const AV_LOG_FATAL: c_int = 8;
+pub const AV_LOG_ERROR: c_int = 16;
+pub const AV_LOG_WARNING: c_int = 24;
+pub const AV_LOG_INFO: c_int = 32;
+pub const AV_LOG_VERBOSE: c_int = 40;
+pub const AV_LOG_DEBUG: c_int = 48;
+
+/// Opaque stand-in for the platform `va_list`, which is only ever passed through to FFmpeg.
+pub type va_list = *mut c_void;
+
+pub type av_log_callback = extern "C" fn(avcl: *mut c_void,
+                                         level: c_int,
+                                         fmt: *const c_char,
+                                         vl: va_list);
+
+pub type AVOptionType = c_int;
+
+pub const AV_OPT_TYPE_FLAGS: AVOptionType          = 0;
+pub const AV_OPT_TYPE_INT: AVOptionType            = 1;
+pub const AV_OPT_TYPE_INT64: AVOptionType          = 2;
+pub const AV_OPT_TYPE_DOUBLE: AVOptionType         = 3;
+pub const AV_OPT_TYPE_FLOAT: AVOptionType          = 4;
+pub const AV_OPT_TYPE_STRING: AVOptionType         = 5;
+pub const AV_OPT_TYPE_RATIONAL: AVOptionType       = 6;
+pub const AV_OPT_TYPE_BINARY: AVOptionType         = 7;
+pub const AV_OPT_TYPE_DICT: AVOptionType           = 8;
+pub const AV_OPT_TYPE_CONST: AVOptionType          = 128;
+pub const AV_OPT_TYPE_IMAGE_SIZE: AVOptionType     = 0x53495A45; // MKBETAG('S','I','Z','E')
+pub const AV_OPT_TYPE_PIXEL_FMT: AVOptionType      = 0x50464D54; // MKBETAG('P','F','M','T')
+pub const AV_OPT_TYPE_SAMPLE_FMT: AVOptionType     = 0x53464D54; // MKBETAG('S','F','M','T')
+pub const AV_OPT_TYPE_VIDEO_RATE: AVOptionType     = 0x56524154; // MKBETAG('V','R','A','T')
+pub const AV_OPT_TYPE_DURATION: AVOptionType       = 0x44555220; // MKBETAG('D','U','R',' ')
+pub const AV_OPT_TYPE_COLOR: AVOptionType          = 0x434F4C52; // MKBETAG('C','O','L','R')
+pub const AV_OPT_TYPE_CHANNEL_LAYOUT: AVOptionType = 0x43484C41; // MKBETAG('C','H','L','A')
+
+pub const AV_OPT_SEARCH_CHILDREN: c_int = 0x0001;
+
 #[repr(C)]
 pub struct AVBuffer;
+
+#[repr(C)]
+pub struct AVOption {
+    pub name: *const c_char,
+    pub help: *const c_char,
+    pub offset: c_int,
+    pub type_: AVOptionType,
+    /// NB: A union of `int64_t`, `double`, `const char *` and `AVRational`.
+    pub default_val: i64,
+    pub min: c_double,
+    pub max: c_double,
+    pub flags: c_int,
+    pub unit: *const c_char,
+}
+
+#[repr(C)]
+pub struct AVClass {
+    pub class_name: *const c_char,
+    pub item_name: Option<extern "C" fn(ctx: *mut c_void) -> *const c_char>,
+    pub option: *const AVOption,
+    pub version: c_int,
+    pub log_level_offset_offset: c_int,
+    pub parent_log_context_offset: c_int,
+    // More follow...
+}
+
+pub const CODEC_CAP_DRAW_HORIZ_BAND: c_int     = 0x0001;
+pub const CODEC_CAP_DR1: c_int                 = 0x0002;
+pub const CODEC_CAP_TRUNCATED: c_int           = 0x0008;
+pub const CODEC_CAP_DELAY: c_int               = 0x0020;
+pub const CODEC_CAP_SMALL_LAST_FRAME: c_int    = 0x0040;
+pub const CODEC_CAP_SUBFRAMES: c_int           = 0x0100;
+pub const CODEC_CAP_EXPERIMENTAL: c_int        = 0x0200;
+pub const CODEC_CAP_CHANNEL_CONF: c_int        = 0x0400;
+pub const CODEC_CAP_FRAME_THREADS: c_int       = 0x1000;
+pub const CODEC_CAP_SLICE_THREADS: c_int       = 0x2000;
+pub const CODEC_CAP_PARAM_CHANGE: c_int        = 0x4000;
+pub const CODEC_CAP_AUTO_THREADS: c_int        = 0x8000;
+pub const CODEC_CAP_VARIABLE_FRAME_SIZE: c_int = 0x10000;
+pub const CODEC_CAP_INTRA_ONLY: c_int          = 0x40000000;
+pub const CODEC_CAP_LOSSLESS: c_int            = 0x80000000u32 as c_int;
+
+pub const AVDISCARD_NONE: AVDiscard = -16;
+pub const AVDISCARD_DEFAULT: AVDiscard = 0;
+pub const AVDISCARD_NONREF: AVDiscard = 8;
+pub const AVDISCARD_BIDIR: AVDiscard = 16;
+pub const AVDISCARD_NONINTRA: AVDiscard = 24;
+pub const AVDISCARD_NONKEY: AVDiscard = 32;
+pub const AVDISCARD_ALL: AVDiscard = 48;
+
+pub const FF_THREAD_FRAME: c_int = 1;
+pub const FF_THREAD_SLICE: c_int = 2;
+
+pub const FF_EC_GUESS_MVS: c_int = 1;
+pub const FF_EC_DEBLOCK: c_int = 2;
+pub const FF_EC_FAVOR_INTER: c_int = 256;
+
+pub const AV_PIX_FMT_NONE: c_int = -1;
+pub const AV_SAMPLE_FMT_NONE: AVSampleFormat = -1;
+pub const FF_PROFILE_UNKNOWN: c_int = -99;
+
+pub const AV_CODEC_PROP_INTRA_ONLY: c_int = 1 << 0;
+pub const AV_CODEC_PROP_LOSSY: c_int      = 1 << 1;
+pub const AV_CODEC_PROP_LOSSLESS: c_int   = 1 << 2;
+pub const AV_CODEC_PROP_BITMAP_SUB: c_int = 1 << 16;
+pub const AV_CODEC_PROP_TEXT_SUB: c_int   = 1 << 17;
+
 #[repr(C)]
-pub struct AVClass;
+pub struct AVCodecDescriptor {
+    pub id: AVCodecID,
+    pub type_: c_int,
+    pub name: *const c_char,
+    pub long_name: *const c_char,
+    pub props: c_int,
+    // More follow...
+}
+
 #[repr(C)]
-pub struct AVCodec;
+pub struct AVProfile {
+    pub profile: c_int,
+    pub name: *const c_char,
+}
+
+#[repr(C)]
+pub struct AVCodec {
+    pub name: *const c_char,
+    pub long_name: *const c_char,
+    pub type_: c_int,
+    pub id: AVCodecID,
+    pub capabilities: c_int,
+    pub supported_framerates: *const AVRational,
+    pub pix_fmts: *const c_int,
+    pub supported_samplerates: *const c_int,
+    pub sample_fmts: *const AVSampleFormat,
+    pub channel_layouts: *const u64,
+    pub max_lowres: u8,
+    pub priv_class: *const AVClass,
+    pub profiles: *const AVProfile,
+    // More follow...
+}
+
 #[repr(C)]
 pub struct AVCodecContext;
 #[repr(C)]
@@ -56,33 +239,122 @@ pub struct AVCodecInternal;
 #[repr(C)]
 pub struct AVDictionary;
 #[repr(C)]
-pub struct AVFrameSideData;
-#[repr(C)]
 pub struct AVPacket;
 #[repr(C)]
-pub struct AVPacketSideData;
-#[repr(C)]
 pub struct AVPanScan;
 #[repr(C)]
-pub struct AVIOContext;
+pub struct AVIOContext {
+    pub av_class: *const AVClass,
+    pub buffer: *mut u8,
+    pub buffer_size: c_int,
+    pub buf_ptr: *mut u8,
+    pub buf_end: *mut u8,
+    pub opaque: *mut c_void,
+    pub read_packet: Option<extern "C" fn(*mut c_void, *mut u8, c_int) -> c_int>,
+    pub write_packet: Option<extern "C" fn(*mut c_void, *mut u8, c_int) -> c_int>,
+    pub seek: Option<extern "C" fn(*mut c_void, i64, c_int) -> i64>,
+    pub pos: i64,
+    pub must_flush: c_int,
+    pub eof_reached: c_int,
+    pub write_flag: c_int,
+    pub max_packet_size: c_int,
+    pub checksum: c_ulong,
+    pub checksum_ptr: *mut u8,
+    pub update_checksum: Option<extern "C" fn(c_ulong, *const u8, c_uint) -> c_ulong>,
+    pub error: c_int,
+    pub read_pause: Option<extern "C" fn(*mut c_void, c_int) -> c_int>,
+    pub read_seek: Option<extern "C" fn(*mut c_void, c_int, i64, c_int) -> i64>,
+    pub seekable: c_int,
+    // More follow...
+}
 #[repr(C)]
-pub struct AVInputFormat;
+pub struct AVPacketList;
 #[repr(C)]
-pub struct AVOutputFormat;
+pub struct AVFormatInternal;
 #[repr(C)]
-pub struct AVProgram;
+pub struct AVIndexEntry;
 #[repr(C)]
-pub struct AVPacketList;
+pub struct ReSampleContext;
 #[repr(C)]
-pub struct AVFormatInternal;
+pub struct AVCodecTag;
 #[repr(C)]
-pub struct AVChapter;
+pub struct AVBitStreamFilterContext;
+
+pub const AV_PARSER_PTS_NB: usize = 4;
+
+pub const PARSER_FLAG_COMPLETE_FRAMES: c_int = 0x0001;
+
 #[repr(C)]
-pub struct AVCodecParserContext;
+pub struct AVCodecParserContext {
+    pub priv_data: *mut c_void,
+    pub parser: *mut AVCodecParser,
+    pub frame_offset: i64,
+    pub cur_offset: i64,
+    pub next_frame_offset: i64,
+    pub pict_type: c_int,
+    pub repeat_pict: c_int,
+    pub pts: i64,
+    pub dts: i64,
+    pub last_pts: i64,
+    pub last_dts: i64,
+    pub fetch_timestamp: c_int,
+    pub cur_frame_start_index: c_int,
+    pub cur_frame_offset: [i64; AV_PARSER_PTS_NB],
+    pub cur_frame_pts: [i64; AV_PARSER_PTS_NB],
+    pub cur_frame_dts: [i64; AV_PARSER_PTS_NB],
+    pub flags: c_int,
+    pub offset: i64,
+    pub cur_frame_end: [i64; AV_PARSER_PTS_NB],
+    pub key_frame: c_int,
+    pub convergence_duration: i64,
+    pub dts_sync_point: c_int,
+    pub dts_ref_dts_delta: c_int,
+    pub pts_dts_delta: c_int,
+    pub cur_frame_pos: [i64; AV_PARSER_PTS_NB],
+    pub pos: i64,
+    pub last_pos: i64,
+    pub duration: c_int,
+    // More follow...
+}
+
 #[repr(C)]
-pub struct AVIndexEntry;
+pub struct AVCodecParser;
+
 #[repr(C)]
-pub struct ReSampleContext;
+pub struct AVInputFormat {
+    pub name: *const c_char,
+    pub long_name: *const c_char,
+    pub flags: c_int,
+    pub extensions: *const c_char,
+    pub codec_tag: *const *const AVCodecTag,
+    pub priv_class: *const AVClass,
+    pub mime_type: *const c_char,
+    // More follow...
+}
+
+#[repr(C)]
+pub struct AVOutputFormat {
+    pub name: *const c_char,
+    pub long_name: *const c_char,
+    pub mime_type: *const c_char,
+    pub extensions: *const c_char,
+    pub audio_codec: AVCodecID,
+    pub video_codec: AVCodecID,
+    pub subtitle_codec: AVCodecID,
+    pub flags: c_int,
+    pub codec_tag: *const *const AVCodecTag,
+    pub priv_class: *const AVClass,
+    // More follow...
+}
+
+pub const AV_DICT_MATCH_CASE: c_int = 1;
+pub const AV_DICT_IGNORE_SUFFIX: c_int = 2;
+
+#[repr(C)]
+pub struct AVDictionaryEntry {
+    pub key: *mut c_char,
+    pub value: *mut c_char,
+}
 
 #[repr(C)]
 pub struct AVBufferRef {
@@ -217,6 +489,83 @@ pub struct AVCodecContextV362300 {
     pub reget_buffer: extern "C" fn(c: *mut AVCodecContext, pic: *mut AVFrame),
     pub get_buffer2: extern "C" fn(s: *mut AVCodecContext, frame: *mut AVFrame, flags: c_int)
                                    -> c_int,
+    pub refcounted_frames: c_int,
+    pub qcompress: c_float,
+    pub qblur: c_float,
+    pub qmin: c_int,
+    pub qmax: c_int,
+    pub max_qdiff: c_int,
+    pub rc_qsquish: c_float,
+    pub rc_qmod_amp: c_float,
+    pub rc_qmod_freq: c_int,
+    pub rc_buffer_size: c_int,
+    pub rc_override_count: c_int,
+    pub rc_override: *mut c_void,
+    pub rc_eq: *const c_char,
+    pub rc_max_rate: c_int,
+    pub rc_min_rate: c_int,
+    pub rc_buffer_aggressivity: c_float,
+    pub rc_initial_cplx: c_float,
+    pub rc_max_available_vbv_use: c_float,
+    pub rc_min_vbv_overflow_use: c_float,
+    pub rc_initial_buffer_occupancy: c_int,
+    pub coder_type: c_int,
+    pub context_model: c_int,
+    pub lmin: c_int,
+    pub lmax: c_int,
+    pub frame_skip_threshold: c_int,
+    pub frame_skip_factor: c_int,
+    pub frame_skip_exp: c_int,
+    pub frame_skip_cmp: c_int,
+    pub trellis: c_int,
+    pub min_prediction_order: c_int,
+    pub max_prediction_order: c_int,
+    pub timecode_frame_start: i64,
+    // Callbacks that these bindings never call are left as plain pointers.
+    pub rtp_callback: *const c_void,
+    pub rtp_payload_size: c_int,
+    pub mv_bits: c_int,
+    pub header_bits: c_int,
+    pub i_tex_bits: c_int,
+    pub p_tex_bits: c_int,
+    pub i_count: c_int,
+    pub p_count: c_int,
+    pub skip_count: c_int,
+    pub misc_bits: c_int,
+    pub frame_bits: c_int,
+    pub stats_out: *mut c_char,
+    pub stats_in: *mut c_char,
+    pub workaround_bugs: c_int,
+    pub strict_std_compliance: c_int,
+    pub error_concealment: c_int,
+    pub debug: c_int,
+    pub debug_mv: c_int,            // NB: Behind `#ifdef FF_API_DEBUG_MV`!
+    pub err_recognition: c_int,
+    pub reordered_opaque: i64,
+    pub hwaccel: *mut c_void,
+    pub hwaccel_context: *mut c_void,
+    pub error: [u64; AV_NUM_DATA_POINTERS],
+    pub dct_algo: c_int,
+    pub idct_algo: c_int,
+    pub dsp_mask: c_uint,           // NB: Behind `#ifdef FF_API_DSP_MASK`!
+    pub bits_per_coded_sample: c_int,
+    pub bits_per_raw_sample: c_int,
+    pub lowres: c_int,              // NB: Behind `#ifdef FF_API_LOWRES`!
+    pub coded_frame: *mut AVFrame,
+    pub thread_count: c_int,
+    pub thread_type: c_int,
+    pub active_thread_type: c_int,
+    pub thread_safe_callbacks: c_int,
+    pub execute: *const c_void,
+    pub execute2: *const c_void,
+    pub thread_opaque: *mut c_void, // NB: Behind `#ifdef FF_API_THREAD_OPAQUE`!
+    pub profile: c_int,
+    pub level: c_int,
+    pub skip_loop_filter: AVDiscard,
+    pub skip_idct: AVDiscard,
+    pub skip_frame: AVDiscard,
+    pub subtitle_header: *mut u8,
+    pub subtitle_header_size: c_int,
     // More follow...
 }
 
@@ -340,6 +689,83 @@ pub struct AVCodecContextV380D64 {
     pub reget_buffer: extern "C" fn(c: *mut AVCodecContext, pic: *mut AVFrame),
     pub get_buffer2: extern "C" fn(s: *mut AVCodecContext, frame: *mut AVFrame, flags: c_int)
                                    -> c_int,
+    pub refcounted_frames: c_int,
+    pub qcompress: c_float,
+    pub qblur: c_float,
+    pub qmin: c_int,
+    pub qmax: c_int,
+    pub max_qdiff: c_int,
+    pub rc_qsquish: c_float,
+    pub rc_qmod_amp: c_float,
+    pub rc_qmod_freq: c_int,
+    pub rc_buffer_size: c_int,
+    pub rc_override_count: c_int,
+    pub rc_override: *mut c_void,
+    pub rc_eq: *const c_char,
+    pub rc_max_rate: c_int,
+    pub rc_min_rate: c_int,
+    pub rc_buffer_aggressivity: c_float,
+    pub rc_initial_cplx: c_float,
+    pub rc_max_available_vbv_use: c_float,
+    pub rc_min_vbv_overflow_use: c_float,
+    pub rc_initial_buffer_occupancy: c_int,
+    pub coder_type: c_int,
+    pub context_model: c_int,
+    pub lmin: c_int,
+    pub lmax: c_int,
+    pub frame_skip_threshold: c_int,
+    pub frame_skip_factor: c_int,
+    pub frame_skip_exp: c_int,
+    pub frame_skip_cmp: c_int,
+    pub trellis: c_int,
+    pub min_prediction_order: c_int,
+    pub max_prediction_order: c_int,
+    pub timecode_frame_start: i64,
+    // Callbacks that these bindings never call are left as plain pointers.
+    pub rtp_callback: *const c_void,
+    pub rtp_payload_size: c_int,
+    pub mv_bits: c_int,
+    pub header_bits: c_int,
+    pub i_tex_bits: c_int,
+    pub p_tex_bits: c_int,
+    pub i_count: c_int,
+    pub p_count: c_int,
+    pub skip_count: c_int,
+    pub misc_bits: c_int,
+    pub frame_bits: c_int,
+    pub stats_out: *mut c_char,
+    pub stats_in: *mut c_char,
+    pub workaround_bugs: c_int,
+    pub strict_std_compliance: c_int,
+    pub error_concealment: c_int,
+    pub debug: c_int,
+    pub debug_mv: c_int,            // NB: Behind `#ifdef FF_API_DEBUG_MV`!
+    pub err_recognition: c_int,
+    pub reordered_opaque: i64,
+    pub hwaccel: *mut c_void,
+    pub hwaccel_context: *mut c_void,
+    pub error: [u64; AV_NUM_DATA_POINTERS],
+    pub dct_algo: c_int,
+    pub idct_algo: c_int,
+    pub bits_per_coded_sample: c_int,
+    pub bits_per_raw_sample: c_int,
+    pub lowres: c_int,              // NB: Behind `#ifdef FF_API_LOWRES`!
+    pub coded_frame: *mut AVFrame,
+    pub thread_count: c_int,
+    pub thread_type: c_int,
+    pub active_thread_type: c_int,
+    pub thread_safe_callbacks: c_int,
+    pub execute: *const c_void,
+    pub execute2: *const c_void,
+    pub thread_opaque: *mut c_void, // NB: Behind `#ifdef FF_API_THREAD_OPAQUE`!
+    pub nsse_weight: c_int,
+    pub profile: c_int,
+    pub level: c_int,
+    pub skip_loop_filter: AVDiscard,
+    pub skip_idct: AVDiscard,
+    pub skip_frame: AVDiscard,
+    pub subtitle_header: *mut u8,
+    pub subtitle_header_size: c_int,
     // More follow...
 }
 
@@ -399,6 +825,11 @@ macro_rules! ffmpeg_ffi_avpacket_field {
     );
 }
 
+pub const AV_FRAME_FLAG_CORRUPT: c_int = 1 << 0;
+
+pub const FF_DECODE_ERROR_INVALID_BITSTREAM: c_int = 1;
+pub const FF_DECODE_ERROR_MISSING_REFERENCE: c_int = 2;
+
 #[repr(C)]
 pub struct AVFrame {
     pub data: [*mut u8; AV_NUM_DATA_POINTERS],
@@ -516,6 +947,44 @@ impl EitherAVPacket {
     }
 }
 
+pub const AV_PKT_FLAG_KEY: c_int     = 0x0001;
+pub const AV_PKT_FLAG_CORRUPT: c_int = 0x0002;
+
+pub const SUBTITLE_NONE: c_int = 0;
+pub const SUBTITLE_BITMAP: c_int = 1;
+pub const SUBTITLE_TEXT: c_int = 2;
+pub const SUBTITLE_ASS: c_int = 3;
+
+#[repr(C)]
+pub struct AVPicture {
+    pub data: [*mut u8; AV_NUM_DATA_POINTERS],
+    pub linesize: [c_int; AV_NUM_DATA_POINTERS],
+}
+
+#[repr(C)]
+pub struct AVSubtitleRect {
+    pub x: c_int,
+    pub y: c_int,
+    pub w: c_int,
+    pub h: c_int,
+    pub nb_colors: c_int,
+    pub pict: AVPicture,
+    pub type_: c_int,
+    pub text: *mut c_char,
+    pub ass: *mut c_char,
+    pub flags: c_int,
+}
+
+#[repr(C)]
+pub struct AVSubtitle {
+    pub format: u16,
+    pub start_display_time: u32,
+    pub end_display_time: u32,
+    pub num_rects: c_uint,
+    pub rects: *mut *mut AVSubtitleRect,
+    pub pts: i64,
+}
+
 #[repr(C)]
 #[derive(Copy, Debug)]
 pub struct AVRational {
@@ -523,6 +992,116 @@ pub struct AVRational {
     pub den: c_int,
 }
 
+pub type AVFrameSideDataType = c_int;
+
+pub const AV_FRAME_DATA_PANSCAN: AVFrameSideDataType = 0;
+pub const AV_FRAME_DATA_A53_CC: AVFrameSideDataType = 1;
+pub const AV_FRAME_DATA_STEREO3D: AVFrameSideDataType = 2;
+pub const AV_FRAME_DATA_MATRIXENCODING: AVFrameSideDataType = 3;
+pub const AV_FRAME_DATA_DOWNMIX_INFO: AVFrameSideDataType = 4;
+pub const AV_FRAME_DATA_REPLAYGAIN: AVFrameSideDataType = 5;
+pub const AV_FRAME_DATA_DISPLAYMATRIX: AVFrameSideDataType = 6;
+pub const AV_FRAME_DATA_AFD: AVFrameSideDataType = 7;
+pub const AV_FRAME_DATA_MOTION_VECTORS: AVFrameSideDataType = 8;
+pub const AV_FRAME_DATA_SKIP_SAMPLES: AVFrameSideDataType = 9;
+pub const AV_FRAME_DATA_AUDIO_SERVICE_TYPE: AVFrameSideDataType = 10;
+pub const AV_FRAME_DATA_MASTERING_DISPLAY_METADATA: AVFrameSideDataType = 11;
+pub const AV_FRAME_DATA_GOP_TIMECODE: AVFrameSideDataType = 12;
+pub const AV_FRAME_DATA_SPHERICAL: AVFrameSideDataType = 13;
+pub const AV_FRAME_DATA_CONTENT_LIGHT_LEVEL: AVFrameSideDataType = 14;
+
+pub type AVPacketSideDataType = c_int;
+
+// As numbered by `libavcodec` 56 (FFmpeg 2.5).
+pub const AV_PKT_DATA_PALETTE: AVPacketSideDataType = 0;
+pub const AV_PKT_DATA_NEW_EXTRADATA: AVPacketSideDataType = 1;
+pub const AV_PKT_DATA_PARAM_CHANGE: AVPacketSideDataType = 2;
+pub const AV_PKT_DATA_H263_MB_INFO: AVPacketSideDataType = 3;
+pub const AV_PKT_DATA_REPLAYGAIN: AVPacketSideDataType = 4;
+pub const AV_PKT_DATA_DISPLAYMATRIX: AVPacketSideDataType = 5;
+pub const AV_PKT_DATA_STEREO3D: AVPacketSideDataType = 6;
+pub const AV_PKT_DATA_SKIP_SAMPLES: AVPacketSideDataType = 70;
+pub const AV_PKT_DATA_JP_DUALMONO: AVPacketSideDataType = 71;
+pub const AV_PKT_DATA_STRINGS_METADATA: AVPacketSideDataType = 72;
+pub const AV_PKT_DATA_SUBTITLE_POSITION: AVPacketSideDataType = 73;
+pub const AV_PKT_DATA_MATROSKA_BLOCKADDITIONAL: AVPacketSideDataType = 74;
+pub const AV_PKT_DATA_WEBVTT_IDENTIFIER: AVPacketSideDataType = 75;
+pub const AV_PKT_DATA_WEBVTT_SETTINGS: AVPacketSideDataType = 76;
+pub const AV_PKT_DATA_METADATA_UPDATE: AVPacketSideDataType = 77;
+
+#[repr(C)]
+pub struct AVFrameSideData {
+    pub type_: AVFrameSideDataType,
+    pub data: *mut u8,
+    pub size: c_int,
+    pub metadata: *mut AVDictionary,
+}
+
+#[repr(C)]
+pub struct AVPacketSideData {
+    pub data: *mut u8,
+    pub size: c_int,
+    pub type_: AVPacketSideDataType,
+}
+
+pub const AV_STEREO3D_2D: c_int = 0;
+pub const AV_STEREO3D_SIDEBYSIDE: c_int = 1;
+pub const AV_STEREO3D_TOPBOTTOM: c_int = 2;
+pub const AV_STEREO3D_FRAMESEQUENCE: c_int = 3;
+pub const AV_STEREO3D_CHECKERBOARD: c_int = 4;
+pub const AV_STEREO3D_SIDEBYSIDE_QUINCUNX: c_int = 5;
+pub const AV_STEREO3D_LINES: c_int = 6;
+pub const AV_STEREO3D_COLUMNS: c_int = 7;
+
+pub const AV_STEREO3D_FLAG_INVERT: c_int = 1 << 0;
+
+#[repr(C)]
+#[derive(Copy, Debug)]
+pub struct AVStereo3D {
+    pub type_: c_int,
+    pub flags: c_int,
+}
+
+#[repr(C)]
+#[derive(Copy, Debug)]
+pub struct AVReplayGain {
+    pub track_gain: i32,
+    pub track_peak: u32,
+    pub album_gain: i32,
+    pub album_peak: u32,
+}
+
+#[repr(C)]
+#[derive(Copy, Debug)]
+pub struct AVMasteringDisplayMetadata {
+    pub display_primaries: [[AVRational; 2]; 3],
+    pub white_point: [AVRational; 2],
+    pub min_luminance: AVRational,
+    pub max_luminance: AVRational,
+    pub has_primaries: c_int,
+    pub has_luminance: c_int,
+}
+
+#[repr(C)]
+#[derive(Copy, Debug)]
+pub struct AVContentLightMetadata {
+    pub max_cll: c_uint,
+    pub max_fall: c_uint,
+}
+
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct AVMotionVector {
+    pub source: i32,
+    pub w: u8,
+    pub h: u8,
+    pub src_x: i16,
+    pub src_y: i16,
+    pub dst_x: i16,
+    pub dst_y: i16,
+    pub flags: u64,
+}
+
 pub type av_format_control_message = extern "C" fn(s: *mut AVFormatContext,
                                                    type_: c_int,
                                                    data: *mut c_void,
@@ -530,8 +1109,8 @@ pub type av_format_control_message = extern "C" fn(s: *mut AVFormatContext,
 
 #[repr(C)]
 pub struct AVIOInterruptCB {
-    callback: extern "C" fn(*mut c_void) -> c_int,
-    opaque: *mut c_void,
+    pub callback: Option<extern "C" fn(opaque: *mut c_void) -> c_int>,
+    pub opaque: *mut c_void,
 }
 
 #[repr(C)]
@@ -614,6 +1193,29 @@ pub struct AVFormatContext {
     pub dump_separator: *mut u8,
 }
 
+#[repr(C)]
+pub struct AVChapter {
+    pub id: c_int,
+    pub time_base: AVRational,
+    pub start: i64,
+    pub end: i64,
+    pub metadata: *mut AVDictionary,
+}
+
+#[repr(C)]
+pub struct AVProgram {
+    pub id: c_int,
+    pub flags: c_int,
+    pub discard: AVDiscard,
+    pub stream_index: *mut c_uint,
+    pub nb_stream_indexes: c_uint,
+    pub metadata: *mut AVDictionary,
+    pub program_num: c_int,
+    pub pmt_pid: c_int,
+    pub pcr_pid: c_int,
+    // More follow...
+}
+
 #[repr(C)]
 pub struct AVFrac {
     val: i64,
@@ -623,10 +1225,10 @@ pub struct AVFrac {
 
 #[repr(C)]
 pub struct AVProbeData {
-    filename: *const c_char,
-    buf: *mut u8,
-    buf_size: c_int,
-    mime_type: *const c_char,
+    pub filename: *const c_char,
+    pub buf: *mut u8,
+    pub buf_size: c_int,
+    pub mime_type: *const c_char,
 }
 
 #[repr(C)]
@@ -663,7 +1265,7 @@ pub struct AVStream {
     pub sample_aspect_ratio: AVRational,
     pub metadata: *mut AVDictionary,
     pub avg_frame_rate: AVRational,
-    pub attached_pic: AVPacket,
+    pub attached_pic: AVPacketV380D64,
     pub side_data: *mut AVPacketSideData,
     pub nb_side_data: c_int,
     pub event_flags: c_int,
@@ -712,11 +1314,20 @@ extern {
     pub fn avcodec_version() -> c_uint;
     pub fn avcodec_register_all();
     pub fn avcodec_find_decoder(id: AVCodecID) -> *mut AVCodec;
+    pub fn avcodec_find_decoder_by_name(name: *const c_char) -> *mut AVCodec;
+    pub fn avcodec_find_encoder(id: AVCodecID) -> *mut AVCodec;
+    pub fn avcodec_find_encoder_by_name(name: *const c_char) -> *mut AVCodec;
+    pub fn avcodec_descriptor_get(id: AVCodecID) -> *const AVCodecDescriptor;
+    pub fn avcodec_descriptor_get_by_name(name: *const c_char) -> *const AVCodecDescriptor;
+    pub fn av_codec_next(c: *const AVCodec) -> *mut AVCodec;
+    pub fn av_codec_is_encoder(codec: *const AVCodec) -> c_int;
+    pub fn av_codec_is_decoder(codec: *const AVCodec) -> c_int;
     pub fn avcodec_alloc_context3(codec: *const AVCodec) -> *mut AVCodecContext;
     pub fn avcodec_open2(avctx: *mut AVCodecContext,
                          codec: *const AVCodec,
                          options: *mut *mut AVDictionary)
                          -> c_int;
+    pub fn avpicture_get_size(pix_fmt: c_int, width: c_int, height: c_int) -> c_int;
     pub fn avcodec_decode_video2(avctx: *mut AVCodecContext,
                                  picture: *mut AVFrame,
                                  got_picture_ptr: *mut c_int,
@@ -727,9 +1338,57 @@ extern {
                                  got_frame_ptr: *mut c_int,
                                  avpkt: *const AVPacket)
                                  -> c_int;
+    pub fn avcodec_copy_context(dest: *mut AVCodecContext, src: *const AVCodecContext) -> c_int;
+    pub fn avcodec_close(avctx: *mut AVCodecContext) -> c_int;
+    pub fn avcodec_free_context(avctx: *mut *mut AVCodecContext);
+    pub fn av_bitstream_filter_init(name: *const c_char) -> *mut AVBitStreamFilterContext;
+    pub fn av_bitstream_filter_filter(bsfc: *mut AVBitStreamFilterContext,
+                                      avctx: *mut AVCodecContext,
+                                      args: *const c_char,
+                                      poutbuf: *mut *mut u8,
+                                      poutbuf_size: *mut c_int,
+                                      buf: *const u8,
+                                      buf_size: c_int,
+                                      keyframe: c_int)
+                                      -> c_int;
+    pub fn av_bitstream_filter_close(bsf: *mut AVBitStreamFilterContext);
+    pub fn av_parser_init(codec_id: AVCodecID) -> *mut AVCodecParserContext;
+    pub fn av_parser_parse2(s: *mut AVCodecParserContext,
+                            avctx: *mut AVCodecContext,
+                            poutbuf: *mut *mut u8,
+                            poutbuf_size: *mut c_int,
+                            buf: *const u8,
+                            buf_size: c_int,
+                            pts: i64,
+                            dts: i64,
+                            pos: i64)
+                            -> c_int;
+    pub fn av_parser_close(s: *mut AVCodecParserContext);
+    pub fn avcodec_encode_subtitle(avctx: *mut AVCodecContext,
+                                   buf: *mut u8,
+                                   buf_size: c_int,
+                                   sub: *const AVSubtitle)
+                                   -> c_int;
+    pub fn avcodec_decode_subtitle2(avctx: *mut AVCodecContext,
+                                    sub: *mut AVSubtitle,
+                                    got_sub_ptr: *mut c_int,
+                                    avpkt: *mut AVPacket)
+                                    -> c_int;
+    pub fn avsubtitle_free(sub: *mut AVSubtitle);
+    pub fn av_free_packet(pkt: *mut AVPacket);
     pub fn av_codec_set_pkt_timebase(avctx: *mut AVCodecContext, val: AVRational);
     pub fn avcodec_default_get_buffer(s: *mut AVCodecContext, frame: *mut AVFrame) -> c_int;
+    pub fn avcodec_default_get_buffer2(s: *mut AVCodecContext, frame: *mut AVFrame, flags: c_int)
+                                       -> c_int;
+    pub fn avcodec_align_dimensions2(s: *mut AVCodecContext,
+                                     width: *mut c_int,
+                                     height: *mut c_int,
+                                     linesize_align: *mut c_int);
     pub fn av_init_packet(packet: *mut AVPacket);
+    pub fn av_new_packet(pkt: *mut AVPacket, size: c_int) -> c_int;
+    pub fn av_dup_packet(pkt: *mut AVPacket) -> c_int;
+    pub fn av_packet_new_side_data(pkt: *mut AVPacket, type_: AVPacketSideDataType, size: c_int)
+                                   -> *mut u8;
     pub fn av_frame_alloc() -> *mut AVFrame;
     pub fn av_frame_free(frame: *mut *mut AVFrame);
     pub fn avcodec_get_frame_defaults(frame: *mut AVFrame);
@@ -756,12 +1415,31 @@ extern {
 #[link(name="avutil")]
 extern {
     pub fn av_dict_free(m: *mut *mut AVDictionary);
+    pub fn av_dict_get(m: *const AVDictionary,
+                       key: *const c_char,
+                       prev: *const AVDictionaryEntry,
+                       flags: c_int)
+                       -> *mut AVDictionaryEntry;
+    pub fn av_dict_copy(dst: *mut *mut AVDictionary, src: *const AVDictionary, flags: c_int);
     pub fn av_dict_set(pm: *mut *mut AVDictionary,
                        key: *const c_char,
                        value: *const c_char,
                        flags: c_int)
                        -> c_int;
     pub fn av_frame_get_plane_buffer(frame: *mut AVFrame, plane: c_int) -> *mut AVBufferRef;
+    pub fn av_frame_get_best_effort_timestamp(frame: *const AVFrame) -> i64;
+    pub fn av_frame_get_pkt_duration(frame: *const AVFrame) -> i64;
+    pub fn av_frame_get_pkt_pos(frame: *const AVFrame) -> i64;
+    pub fn av_frame_get_metadata(frame: *const AVFrame) -> *mut AVDictionary;
+    pub fn av_frame_get_decode_error_flags(frame: *const AVFrame) -> c_int;
+    pub fn av_frame_get_colorspace(frame: *const AVFrame) -> AVColorSpace;
+    pub fn av_frame_get_color_range(frame: *const AVFrame) -> AVColorRange;
+    pub fn av_frame_set_colorspace(frame: *mut AVFrame, colorspace: AVColorSpace);
+    pub fn av_frame_set_color_range(frame: *mut AVFrame, color_range: AVColorRange);
+    pub fn av_frame_new_side_data(frame: *mut AVFrame,
+                                  type_: AVFrameSideDataType,
+                                  size: c_int)
+                                  -> *mut AVFrameSideData;
     pub fn av_opt_get_double(obj: *mut c_void,
                              name: *const c_char,
                              search_flags: c_int,
@@ -772,15 +1450,151 @@ extern {
                         search_flags: c_int,
                         out_val: *mut AVRational)
                         -> c_int;
+    pub fn av_opt_next(obj: *mut c_void, prev: *const AVOption) -> *const AVOption;
+    pub fn av_opt_set(obj: *mut c_void,
+                      name: *const c_char,
+                      val: *const c_char,
+                      search_flags: c_int)
+                      -> c_int;
+    pub fn av_opt_set_int(obj: *mut c_void,
+                          name: *const c_char,
+                          val: i64,
+                          search_flags: c_int)
+                          -> c_int;
+    pub fn av_opt_set_double(obj: *mut c_void,
+                             name: *const c_char,
+                             val: c_double,
+                             search_flags: c_int)
+                             -> c_int;
+    pub fn av_opt_set_q(obj: *mut c_void,
+                        name: *const c_char,
+                        val: AVRational,
+                        search_flags: c_int)
+                        -> c_int;
+    pub fn av_opt_set_bin(obj: *mut c_void,
+                          name: *const c_char,
+                          val: *const u8,
+                          size: c_int,
+                          search_flags: c_int)
+                          -> c_int;
+    pub fn av_opt_set_image_size(obj: *mut c_void,
+                                 name: *const c_char,
+                                 w: c_int,
+                                 h: c_int,
+                                 search_flags: c_int)
+                                 -> c_int;
+    pub fn av_opt_set_pixel_fmt(obj: *mut c_void,
+                                name: *const c_char,
+                                fmt: c_int,
+                                search_flags: c_int)
+                                -> c_int;
+    pub fn av_opt_set_sample_fmt(obj: *mut c_void,
+                                 name: *const c_char,
+                                 fmt: AVSampleFormat,
+                                 search_flags: c_int)
+                                 -> c_int;
+    pub fn av_opt_set_channel_layout(obj: *mut c_void,
+                                     name: *const c_char,
+                                     ch_layout: i64,
+                                     search_flags: c_int)
+                                     -> c_int;
+    pub fn av_opt_set_dict_val(obj: *mut c_void,
+                               name: *const c_char,
+                               val: *const AVDictionary,
+                               search_flags: c_int)
+                               -> c_int;
+    pub fn av_opt_get(obj: *mut c_void,
+                      name: *const c_char,
+                      search_flags: c_int,
+                      out_val: *mut *mut u8)
+                      -> c_int;
+    pub fn av_opt_get_int(obj: *mut c_void,
+                          name: *const c_char,
+                          search_flags: c_int,
+                          out_val: *mut i64)
+                          -> c_int;
+    pub fn av_opt_get_image_size(obj: *mut c_void,
+                                 name: *const c_char,
+                                 search_flags: c_int,
+                                 w_out: *mut c_int,
+                                 h_out: *mut c_int)
+                                 -> c_int;
+    pub fn av_opt_get_pixel_fmt(obj: *mut c_void,
+                                name: *const c_char,
+                                search_flags: c_int,
+                                out_fmt: *mut c_int)
+                                -> c_int;
+    pub fn av_opt_get_sample_fmt(obj: *mut c_void,
+                                 name: *const c_char,
+                                 search_flags: c_int,
+                                 out_fmt: *mut AVSampleFormat)
+                                 -> c_int;
+    pub fn av_opt_get_channel_layout(obj: *mut c_void,
+                                     name: *const c_char,
+                                     search_flags: c_int,
+                                     ch_layout: *mut i64)
+                                     -> c_int;
+    pub fn av_opt_get_dict_val(obj: *mut c_void,
+                               name: *const c_char,
+                               search_flags: c_int,
+                               out_val: *mut *mut AVDictionary)
+                               -> c_int;
     pub fn av_samples_get_buffer_size(linesize: *mut c_int,
                                       nb_channels: c_int,
                                       nb_samples: c_int,
                                       sample_fmt: AVSampleFormat,
                                       align: c_int)
                                       -> c_int;
+    pub fn av_samples_fill_arrays(audio_data: *mut *mut u8,
+                                  linesize: *mut c_int,
+                                  buf: *const u8,
+                                  nb_channels: c_int,
+                                  nb_samples: c_int,
+                                  sample_fmt: AVSampleFormat,
+                                  align: c_int)
+                                  -> c_int;
+    pub fn av_sample_fmt_is_planar(sample_fmt: AVSampleFormat) -> c_int;
+    pub fn av_image_fill_linesizes(linesizes: *mut c_int, pix_fmt: c_int, width: c_int) -> c_int;
+    pub fn av_image_fill_pointers(data: *mut *mut u8,
+                                  pix_fmt: c_int,
+                                  height: c_int,
+                                  ptr: *mut u8,
+                                  linesizes: *const c_int)
+                                  -> c_int;
+    pub fn av_buffer_create(data: *mut u8,
+                            size: c_int,
+                            free: extern "C" fn(opaque: *mut c_void, data: *mut u8),
+                            opaque: *mut c_void,
+                            flags: c_int)
+                            -> *mut AVBufferRef;
+    pub fn av_frame_get_channels(frame: *const AVFrame) -> c_int;
+
+    pub fn av_log_set_level(level: c_int);
+    pub fn av_log_get_level() -> c_int;
+    pub fn av_log_set_callback(callback: av_log_callback);
+    pub fn av_log_default_callback(avcl: *mut c_void,
+                                   level: c_int,
+                                   fmt: *const c_char,
+                                   vl: va_list);
+    pub fn av_log_format_line(ptr: *mut c_void,
+                              level: c_int,
+                              fmt: *const c_char,
+                              vl: va_list,
+                              line: *mut c_char,
+                              line_size: c_int,
+                              print_prefix: *mut c_int);
+    pub fn av_default_item_name(ctx: *mut c_void) -> *const c_char;
+
+    pub fn av_gettime_relative() -> i64;
+    pub fn av_display_rotation_get(matrix: *const i32) -> c_double;
+    pub fn av_rescale_q(a: i64, bq: AVRational, cq: AVRational) -> i64;
+    pub fn av_d2q(d: c_double, max: c_int) -> AVRational;
 
     pub fn av_malloc(size: size_t) -> *mut c_void;
+    pub fn av_mallocz(size: size_t) -> *mut c_void;
     pub fn av_free(ptr: *mut c_void);
+    pub fn av_strdup(s: *const c_char) -> *mut c_char;
+    pub fn av_dynarray_add(tab_ptr: *mut c_void, nb_ptr: *mut c_int, elem: *mut c_void);
 }
 
 pub type AVIOPacketFn = extern "C" fn(opaque: *mut c_void,
@@ -796,6 +1610,20 @@ pub type AVIOSeekFn = extern "C" fn(opaque: *mut c_void,
 extern {
     pub fn av_register_all();
     pub fn avformat_version() -> c_uint;
+    pub fn av_iformat_next(f: *const AVInputFormat) -> *mut AVInputFormat;
+    pub fn av_oformat_next(f: *const AVOutputFormat) -> *mut AVOutputFormat;
+    pub fn av_find_input_format(short_name: *const c_char) -> *mut AVInputFormat;
+    pub fn av_probe_input_format3(pd: *mut AVProbeData,
+                                  is_opened: c_int,
+                                  score_ret: *mut c_int)
+                                  -> *mut AVInputFormat;
+    pub fn av_probe_input_buffer2(pb: *mut AVIOContext,
+                                  fmt: *mut *mut AVInputFormat,
+                                  filename: *const c_char,
+                                  logctx: *mut c_void,
+                                  offset: c_uint,
+                                  max_probe_size: c_uint)
+                                  -> c_int;
 
     pub fn avformat_open_input(ps: *mut *mut AVFormatContext,
                                filename: *const c_char,
@@ -820,4 +1648,29 @@ extern {
                               -> *mut AVIOContext;
 
     pub fn av_read_frame(s: *mut AVFormatContext, pkg: *mut AVPacket) -> c_int;
+
+    pub fn avformat_alloc_output_context2(ctx: *mut *mut AVFormatContext,
+                                          oformat: *mut AVOutputFormat,
+                                          format_name: *const c_char,
+                                          filename: *const c_char)
+                                          -> c_int;
+    pub fn avformat_new_stream(s: *mut AVFormatContext, c: *const AVCodec) -> *mut AVStream;
+    pub fn avformat_write_header(s: *mut AVFormatContext, options: *mut *mut AVDictionary) -> c_int;
+    pub fn av_interleaved_write_frame(s: *mut AVFormatContext, pkt: *mut AVPacket) -> c_int;
+    pub fn av_write_trailer(s: *mut AVFormatContext) -> c_int;
+
+    pub fn avio_open(s: *mut *mut AVIOContext, url: *const c_char, flags: c_int) -> c_int;
+    pub fn avio_flush(s: *mut AVIOContext);
+    pub fn avio_open2(s: *mut *mut AVIOContext,
+                      url: *const c_char,
+                      flags: c_int,
+                      int_cb: *const AVIOInterruptCB,
+                      options: *mut *mut AVDictionary)
+                      -> c_int;
+    pub fn avio_closep(s: *mut *mut AVIOContext) -> c_int;
+    pub fn avio_read(s: *mut AVIOContext, buf: *mut u8, size: c_int) -> c_int;
+    pub fn avio_write(s: *mut AVIOContext, buf: *const u8, size: c_int);
+    pub fn avio_seek(s: *mut AVIOContext, offset: i64, whence: c_int) -> i64;
+    pub fn avio_size(s: *mut AVIOContext) -> i64;
+    pub fn avformat_network_init() -> c_int;
 }
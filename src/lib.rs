@@ -1,6 +1,11 @@
 #![feature(libc, std_misc, unsafe_destructor, core, old_io, collections)]
 
 extern crate libc;
+#[cfg(feature = "log")]
+#[macro_use]
+extern crate log;
+#[cfg(feature = "async")]
+extern crate futures;
 
 #[allow(non_camel_case_types)]
 #[macro_use]
@@ -0,0 +1,582 @@
+//! Demuxing and decoding on a worker thread, for callers that must not block on I/O.
+//!
+//! FFmpeg reads its input through blocking callbacks, so these run on a dedicated thread, which
+//! hands packets or decoded frames back through a bounded channel. The worker stalls once
+//! `capacity` of them are waiting, and stops once the receiving end is dropped.
+//!
+//! `PacketStream` and `FrameStream` read from an `AsyncRead + AsyncSeek` source and are
+//! `futures` streams themselves. `ThreadedDecoder` does the same for a blocking `Reader`, and
+//! hands frames back as an `Iterator`.
+//!
+//! The worker waits for each read and seek of an asynchronous source with `block_on`, outside of
+//! any runtime. Sources that must be polled within their runtime, such as tokio's `File`, need to
+//! be fed from it instead, e.g. through a pipe or channel.
+//!
+//! Dropping a stream or decoder can't interrupt a read that is already blocked in the source, so
+//! a worker waiting on a source that never returns is leaked along with its thread.
+
+use std::cmp::min;
+use std::io::{ self, SeekFrom };
+use std::old_io::{ self, IoError, IoResult, Reader, Seek };
+use std::pin::Pin;
+use std::sync::mpsc::{ sync_channel, Receiver, TryRecvError };
+use std::task::{ Context, Poll };
+use std::thread;
+use futures::channel::{ mpsc, oneshot };
+use futures::executor::block_on;
+use futures::future::{ self, Future };
+use futures::io::{ AsyncRead, AsyncReadExt, AsyncSeek, AsyncSeekExt };
+use futures::stream::Stream;
+use libc::c_int;
+
+use avformat::{ AvFormatContext, AvioContext, AvioContextCallbacks, AvioContextReader, AvioContextSeekableReader,
+                CancelToken, OpenOptions };
+use avcodec::{ AvCodecContext, AvPacket };
+use avutil::{ AvDictionary, AvFrame };
+use ffi::{ self, AVRational, AVMEDIA_TYPE_AUDIO, AVMEDIA_TYPE_VIDEO };
+
+/// Describes the stream a `ThreadedDecoder` or `FrameStream` decodes, or one of the streams of a
+/// `PacketStream`.
+#[derive(Copy)]
+pub struct StreamParameters {
+    pub stream_index: usize,
+    pub media_type: c_int,
+    pub time_base: AVRational,
+    pub sample_rate: i32,
+    pub channels: i32,
+}
+
+/// Decodes the first audio or video stream of an input on a worker thread.
+pub struct ThreadedDecoder {
+    frames: Receiver<Result<AvFrame, c_int>>,
+    parameters: StreamParameters,
+    cancel: CancelToken,
+}
+
+impl ThreadedDecoder {
+    /// Opens `reader` on a new thread and starts decoding the first stream of `media_type`
+    /// (`AVMEDIA_TYPE_AUDIO` or `AVMEDIA_TYPE_VIDEO`), keeping up to `capacity` frames ready.
+    ///
+    /// Blocks until the input has been probed and the decoder opened.
+    pub fn spawn<R: Reader + Send + 'static>(reader: R, media_type: c_int, capacity: usize)
+                                             -> Result<ThreadedDecoder, c_int> {
+        let cancel = CancelToken::new();
+        let callbacks = AvioContextReader::with_cancel(reader, cancel.clone());
+        ThreadedDecoder::spawn_with(callbacks, cancel, media_type, capacity)
+    }
+
+    /// Like `spawn`, for input that can seek, which formats such as MP4 may need.
+    pub fn spawn_seekable<R: Reader + Seek + Send + 'static>(reader: R, media_type: c_int, capacity: usize)
+                                                             -> Result<ThreadedDecoder, c_int> {
+        let cancel = CancelToken::new();
+        let callbacks = AvioContextSeekableReader::with_cancel(reader, cancel.clone());
+        ThreadedDecoder::spawn_with(callbacks, cancel, media_type, capacity)
+    }
+
+    fn spawn_with<C: AvioContextCallbacks + Send + 'static>(callbacks: C, cancel: CancelToken, media_type: c_int,
+                                                            capacity: usize)
+                                                            -> Result<ThreadedDecoder, c_int> {
+        if media_type != AVMEDIA_TYPE_AUDIO && media_type != AVMEDIA_TYPE_VIDEO {
+            return Err(-1)
+        }
+
+        let (setup_sender, setup) = sync_channel(1);
+        let (frame_sender, frames) = sync_channel(capacity);
+        let token = cancel.clone();
+        thread::spawn(move || {
+            decode(callbacks,
+                   media_type,
+                   token,
+                   move |parameters| setup_sender.send(parameters).is_ok(),
+                   move |frame| frame_sender.send(frame).is_ok())
+        });
+
+        match setup.recv() {
+            Ok(Ok(parameters)) => Ok(ThreadedDecoder {
+                frames: frames,
+                parameters: parameters,
+                cancel: cancel,
+            }),
+            Ok(Err(err)) => Err(err),
+            // The worker panicked.
+            Err(_) => Err(-1),
+        }
+    }
+
+    pub fn parameters(&self) -> &StreamParameters {
+        &self.parameters
+    }
+
+    /// Returns the next frame if one is ready, without blocking.
+    pub fn try_next(&self) -> Result<Result<AvFrame, c_int>, TryRecvError> {
+        self.frames.try_recv()
+    }
+}
+
+impl Iterator for ThreadedDecoder {
+    type Item = Result<AvFrame, c_int>;
+
+    /// Blocks until the next frame is decoded. Packets that fail to decode are reported and
+    /// skipped; a read error ends the stream after it has been returned.
+    fn next(&mut self) -> Option<Result<AvFrame, c_int>> {
+        self.frames.recv().ok()
+    }
+}
+
+impl Drop for ThreadedDecoder {
+    fn drop(&mut self) {
+        // Stops the worker at its next read, or at its next send, which fails once the receiver
+        // is gone. A read already blocked in the reader has to return first.
+        self.cancel.cancel();
+    }
+}
+
+/// The frames of the first audio or video stream of an asynchronous source, decoded on a worker
+/// thread. Like `ThreadedDecoder`, decode errors are reported and skipped, and a read error ends
+/// the stream after it has been returned.
+pub struct FrameStream {
+    frames: mpsc::Receiver<Result<AvFrame, c_int>>,
+    parameters: StreamParameters,
+    cancel: CancelToken,
+}
+
+impl FrameStream {
+    /// Opens `source` on a new thread and starts decoding the first stream of `media_type`
+    /// (`AVMEDIA_TYPE_AUDIO` or `AVMEDIA_TYPE_VIDEO`), keeping up to `capacity` frames ready.
+    ///
+    /// Resolves once the input has been probed and the decoder opened.
+    pub fn open<S>(source: S, media_type: c_int, capacity: usize) -> Opening<FrameStream>
+                   where S: AsyncRead + AsyncSeek + Unpin + Send + 'static {
+        let cancel = CancelToken::new();
+        let (setup_sender, setup) = oneshot::channel();
+        if media_type != AVMEDIA_TYPE_AUDIO && media_type != AVMEDIA_TYPE_VIDEO {
+            let _ = setup_sender.send(Err(-1));
+            return Opening::new(setup, cancel)
+        }
+
+        let token = cancel.clone();
+        thread::spawn(move || {
+            let callbacks = AsyncSource::new(source, token.clone());
+            let (mut frame_sender, frames) = mpsc::channel(capacity);
+            let stream_cancel = token.clone();
+            let setup = move |parameters: Result<StreamParameters, c_int>| {
+                setup_sender.send(parameters.map(|parameters| FrameStream {
+                    frames: frames,
+                    parameters: parameters,
+                    cancel: stream_cancel,
+                })).is_ok()
+            };
+            decode(callbacks, media_type, token, setup, move |frame| send_blocking(&mut frame_sender, frame).is_ok())
+        });
+        Opening::new(setup, cancel)
+    }
+
+    pub fn parameters(&self) -> &StreamParameters {
+        &self.parameters
+    }
+}
+
+impl Stream for FrameStream {
+    type Item = Result<AvFrame, c_int>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<AvFrame, c_int>>> {
+        Pin::new(&mut self.frames).poll_next(cx)
+    }
+}
+
+impl Drop for FrameStream {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+    }
+}
+
+/// The packets of every stream of an asynchronous source, demuxed on a worker thread. A read
+/// error ends the stream after it has been returned.
+///
+/// Packets own their data; call `clear` on each once done with it.
+pub struct PacketStream {
+    packets: mpsc::Receiver<Result<AvPacket<'static>, c_int>>,
+    streams: Vec<StreamParameters>,
+    cancel: CancelToken,
+}
+
+impl PacketStream {
+    /// Opens `source` on a new thread and starts demuxing it, keeping up to `capacity` packets
+    /// ready.
+    ///
+    /// Resolves once the input has been probed and its streams found.
+    pub fn open<S>(source: S, capacity: usize) -> Opening<PacketStream>
+                   where S: AsyncRead + AsyncSeek + Unpin + Send + 'static {
+        let cancel = CancelToken::new();
+        let (setup_sender, setup) = oneshot::channel();
+        let token = cancel.clone();
+        thread::spawn(move || {
+            let callbacks = AsyncSource::new(source, token.clone());
+            let (mut packet_sender, packets) = mpsc::channel(capacity);
+            let stream_cancel = token.clone();
+            let setup = move |streams: Result<Vec<StreamParameters>, c_int>| {
+                setup_sender.send(streams.map(|streams| PacketStream {
+                    packets: packets,
+                    streams: streams,
+                    cancel: stream_cancel,
+                })).is_ok()
+            };
+            demux(callbacks, token, setup, move |packet| {
+                match send_blocking(&mut packet_sender, packet) {
+                    Ok(()) => true,
+                    Err(Ok(mut packet)) => {
+                        packet.clear();
+                        false
+                    }
+                    Err(Err(_)) => false,
+                }
+            })
+        });
+        Opening::new(setup, cancel)
+    }
+
+    /// The streams of the input, indexed by `AvPacket::stream_index`.
+    pub fn streams(&self) -> &[StreamParameters] {
+        &self.streams
+    }
+}
+
+impl Stream for PacketStream {
+    type Item = Result<AvPacket<'static>, c_int>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Result<AvPacket<'static>, c_int>>> {
+        Pin::new(&mut self.packets).poll_next(cx)
+    }
+}
+
+impl Drop for PacketStream {
+    fn drop(&mut self) {
+        self.cancel.cancel();
+        // Packets don't free their data when dropped, so release those nobody received.
+        self.packets.close();
+        while let Ok(Some(result)) = self.packets.try_next() {
+            if let Ok(mut packet) = result {
+                packet.clear();
+            }
+        }
+    }
+}
+
+/// A `FrameStream` or `PacketStream` being opened on its worker thread. Dropping it before it
+/// resolves stops the worker at its next read.
+pub struct Opening<T> {
+    setup: oneshot::Receiver<Result<T, c_int>>,
+    cancel: Option<CancelToken>,
+}
+
+impl<T> Opening<T> {
+    fn new(setup: oneshot::Receiver<Result<T, c_int>>, cancel: CancelToken) -> Opening<T> {
+        Opening {
+            setup: setup,
+            cancel: Some(cancel),
+        }
+    }
+}
+
+impl<T> Future for Opening<T> {
+    type Output = Result<T, c_int>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<T, c_int>> {
+        match Pin::new(&mut self.setup).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(result) => {
+                // From here on the stream, if any, cancels the worker.
+                self.cancel = None;
+                // The worker panicked if it went away without sending a result.
+                Poll::Ready(result.unwrap_or(Err(-1)))
+            }
+        }
+    }
+}
+
+impl<T> Drop for Opening<T> {
+    fn drop(&mut self) {
+        if let Some(ref cancel) = self.cancel {
+            cancel.cancel();
+        }
+    }
+}
+
+/// Runs the `AvioContext` callbacks on an asynchronous source, waiting for each read and seek.
+struct AsyncSource<S> {
+    source: S,
+    cancel: CancelToken,
+}
+
+impl<S> AsyncSource<S> {
+    fn new(source: S, cancel: CancelToken) -> AsyncSource<S> {
+        AsyncSource {
+            source: source,
+            cancel: cancel,
+        }
+    }
+
+    fn check_cancelled(&self) -> IoResult<()> {
+        if self.cancel.is_cancelled() {
+            Err(IoError {
+                kind: old_io::ConnectionAborted,
+                desc: "cancelled",
+                detail: None,
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncSeek + Unpin> AvioContextCallbacks for AsyncSource<S> {
+    fn read(&mut self, data: &mut [u8]) -> IoResult<usize> {
+        try!(self.check_cancelled());
+        block_on(self.source.read(data)).map_err(to_io_error)
+    }
+
+    fn seek(&mut self, offset: i64, whence: i32) -> IoResult<u64> {
+        try!(self.check_cancelled());
+        if whence & ffi::AVSEEK_SIZE != 0 {
+            let position = try!(block_on(self.source.seek(SeekFrom::Current(0))).map_err(to_io_error));
+            let size = try!(block_on(self.source.seek(SeekFrom::End(0))).map_err(to_io_error));
+            try!(block_on(self.source.seek(SeekFrom::Start(position))).map_err(to_io_error));
+            return Ok(size)
+        }
+        let position = match whence & !ffi::AVSEEK_FORCE {
+            ffi::SEEK_SET if offset >= 0 => SeekFrom::Start(offset as u64),
+            ffi::SEEK_CUR => SeekFrom::Current(offset),
+            ffi::SEEK_END => SeekFrom::End(offset),
+            _ => return Err(IoError {
+                kind: old_io::InvalidInput,
+                desc: "invalid seek",
+                detail: None,
+            }),
+        };
+        block_on(self.source.seek(position)).map_err(to_io_error)
+    }
+}
+
+/// Carries an error of an asynchronous source over to the callbacks, keeping what FFmpeg's error
+/// code is derived from.
+fn to_io_error(error: io::Error) -> IoError {
+    let kind = match error.kind() {
+        io::ErrorKind::NotFound => old_io::FileNotFound,
+        io::ErrorKind::PermissionDenied => old_io::PermissionDenied,
+        io::ErrorKind::ConnectionRefused => old_io::ConnectionRefused,
+        io::ErrorKind::ConnectionReset => old_io::ConnectionReset,
+        io::ErrorKind::ConnectionAborted => old_io::ConnectionAborted,
+        io::ErrorKind::NotConnected => old_io::NotConnected,
+        io::ErrorKind::BrokenPipe => old_io::BrokenPipe,
+        io::ErrorKind::TimedOut => old_io::TimedOut,
+        io::ErrorKind::InvalidInput => old_io::InvalidInput,
+        _ => old_io::OtherIoError,
+    };
+    IoError {
+        kind: kind,
+        desc: "asynchronous source failed",
+        detail: Some(error.to_string()),
+    }
+}
+
+/// Waits for room in the channel, then sends `item`. Hands it back if the receiver is gone.
+fn send_blocking<T>(sender: &mut mpsc::Sender<T>, item: T) -> Result<(), T> {
+    if block_on(future::poll_fn(|cx| sender.poll_ready(cx))).is_err() {
+        return Err(item)
+    }
+    sender.try_send(item).map_err(|err| err.into_inner())
+}
+
+/// Opens the input with `cancel` as its interrupt callback, and finds its streams.
+fn open_input<C: AvioContextCallbacks>(callbacks: C, cancel: &CancelToken)
+                                       -> Result<AvFormatContext<AvioContext<C>>, c_int> {
+    let avio = AvioContext::new(0x1000, false, callbacks);
+    let open_options = OpenOptions::new().interrupt(cancel.interrupt_callback());
+    let (context, _) = AvFormatContext::open_input_with(avio, open_options, AvDictionary::new());
+    let context = try!(context.map_err(|err| err.to_averror()));
+    let (result, _) = context.find_stream_info(AvDictionary::new());
+    try!(result);
+    Ok(context)
+}
+
+fn demux<C, S, F>(callbacks: C, cancel: CancelToken, setup: S, mut send: F)
+                  where C: AvioContextCallbacks,
+                        S: FnOnce(Result<Vec<StreamParameters>, c_int>) -> bool,
+                        F: FnMut(Result<AvPacket<'static>, c_int>) -> bool {
+    let context = match open_input(callbacks, &cancel) {
+        Ok(context) => context,
+        Err(err) => {
+            setup(Err(err));
+            return
+        }
+    };
+    let count = unsafe {
+        (*context.context).nb_streams as usize
+    };
+    if !setup(Ok((0..count).map(|index| stream_parameters(&context, index)).collect())) {
+        return
+    }
+
+    loop {
+        let mut packet = AvPacket::empty();
+        match context.read_packet(&mut packet) {
+            Ok(()) => {}
+            Err(ffi::AVERROR_EOF) => return,
+            Err(err) => {
+                if !cancel.is_cancelled() {
+                    send(Err(err));
+                }
+                return
+            }
+        }
+
+        // The packet may point into the demuxer's buffers, which go away with it.
+        let result = unsafe {
+            ffi::av_dup_packet(packet.packet.ptr())
+        };
+        let result = if result < 0 {
+            packet.clear();
+            Err(result)
+        } else {
+            Ok(packet)
+        };
+        if !send(result) {
+            return
+        }
+    }
+}
+
+fn stream_parameters<C>(context: &AvFormatContext<C>, index: usize) -> StreamParameters {
+    unsafe {
+        let stream = *(*context.context).streams.offset(index as isize);
+        let codec = ffi::EitherAVCodecContext::from_ptr((*stream).codec);
+        StreamParameters {
+            stream_index: index,
+            media_type: *ffmpeg_ffi_avcodeccontext_field!(codec, codec_type),
+            time_base: context.stream_time_base(index),
+            sample_rate: *ffmpeg_ffi_avcodeccontext_field!(codec, sample_rate),
+            channels: *ffmpeg_ffi_avcodeccontext_field!(codec, channels),
+        }
+    }
+}
+
+fn decode<C, S, F>(callbacks: C, media_type: c_int, cancel: CancelToken, setup: S, mut send: F)
+                   where C: AvioContextCallbacks,
+                         S: FnOnce(Result<StreamParameters, c_int>) -> bool,
+                         F: FnMut(Result<AvFrame, c_int>) -> bool {
+    let context = match open_input(callbacks, &cancel) {
+        Ok(context) => context,
+        Err(err) => {
+            setup(Err(err));
+            return
+        }
+    };
+    let stream_index = match context.find_stream(media_type) {
+        Some(index) => index,
+        None => {
+            setup(Err(-1));
+            return
+        }
+    };
+
+    // Frames must own their data rather than point into the decoder's, to leave the thread.
+    let mut options = AvDictionary::new();
+    options.set("refcounted_frames", "1");
+    let (decoder, _) = context.open_stream(stream_index, options);
+    let decoder = match decoder {
+        Ok(decoder) => decoder,
+        Err(err) => {
+            setup(Err(err));
+            return
+        }
+    };
+    let parameters = StreamParameters {
+        stream_index: stream_index,
+        media_type: media_type,
+        time_base: context.stream_time_base(stream_index),
+        sample_rate: decoder.sample_rate(),
+        channels: decoder.channels(),
+    };
+    if !setup(Ok(parameters)) {
+        return
+    }
+
+    let mut packet = AvPacket::empty();
+    loop {
+        packet.clear();
+        match context.read_packet(&mut packet) {
+            Ok(()) => {}
+            Err(ffi::AVERROR_EOF) => break,
+            Err(err) => {
+                if !cancel.is_cancelled() {
+                    send(Err(err));
+                }
+                packet.clear();
+                return
+            }
+        }
+        if packet.stream_index() != stream_index {
+            continue
+        }
+
+        // Audio packets may hold several frames; video packets hold one.
+        while packet.has_data() {
+            let result = match decode_packet(&decoder, media_type, &mut packet) {
+                Ok(Some(frame)) => Ok(frame),
+                Ok(None) => continue,
+                Err(err) => {
+                    // The rest of the packet is unusable.
+                    packet.clear();
+                    Err(err)
+                }
+            };
+            if !send(result) {
+                packet.clear();
+                return
+            }
+        }
+    }
+    packet.clear();
+
+    // Drain the frames the decoder is still holding back.
+    let mut flush = AvPacket::empty();
+    while let Ok(Some(frame)) = decode_packet(&decoder, media_type, &mut flush) {
+        if !send(Ok(frame)) {
+            return
+        }
+    }
+}
+
+/// Decodes from the start of `packet`, consuming what the decoder used. Returns `Ok(None)` if no
+/// frame was completed.
+fn decode_packet(decoder: &AvCodecContext, media_type: c_int, packet: &mut AvPacket)
+                 -> Result<Option<AvFrame>, c_int> {
+    let frame = AvFrame::new();
+    let mut got_frame = 0;
+    let result = unsafe {
+        if media_type == AVMEDIA_TYPE_AUDIO {
+            ffi::avcodec_decode_audio4(decoder.context.ptr(), frame.frame, &mut got_frame, packet.packet.ptr())
+        } else {
+            ffi::avcodec_decode_video2(decoder.context.ptr(), frame.frame, &mut got_frame, packet.packet.ptr())
+        }
+    };
+    if result < 0 {
+        return Err(result)
+    }
+
+    // Video decoders always take the whole packet, and so does an audio decoder that took none
+    // of it, which would otherwise be fed the same data forever.
+    let size = *ffmpeg_ffi_avpacket_field!(packet.packet, size);
+    let consumed = if media_type == AVMEDIA_TYPE_AUDIO && result > 0 { min(result, size) } else { size };
+    *ffmpeg_ffi_avpacket_field!(packet.packet, mut size) -= consumed;
+    unsafe {
+        let data = ffmpeg_ffi_avpacket_field!(packet.packet, mut data);
+        *data = data.offset(consumed as isize);
+    }
+
+    if got_frame != 0 {
+        Ok(Some(frame))
+    } else {
+        Ok(None)
+    }
+}
@@ -11,7 +11,7 @@ use ffi::{ AVMEDIA_TYPE_AUDIO };
 
 pub struct AudioDecoder<'a, R, T = i16> {
     context: AvFormatContext<AvioContext<AvioContextReader<R>>>,
-    decoder: AvCodecContext,
+    decoder: AvCodecContext<'static>,
     packet: AvPacket<'a>,
     frame: AvFrame,
     frame_size: usize,
@@ -30,7 +30,8 @@ impl<'a, R: Reader + 'a, T: SampleFormatType> AudioDecoder<'a, R, T> {
         try!(res);
 
         let stream_index = try!(context.find_stream(AVMEDIA_TYPE_AUDIO).ok_or(-1));
-        let (decoder, _) = context.open_stream(stream_index, AvDictionary::new());
+        // An owned copy, so that the decoder doesn't borrow the context stored alongside it.
+        let (decoder, _) = context.open_stream_copy(stream_index, AvDictionary::new());
         let decoder = try!(decoder);
         unsafe {
             *ffmpeg_ffi_avcodeccontext_field!(decoder.context, mut sample_fmt) = SampleFormat::from_type::<T>().sample_fmt();
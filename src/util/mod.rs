@@ -1,7 +1,13 @@
 mod resample;
 mod sampleformat;
 mod audiodecoder;
+mod subtitlereader;
+#[cfg(feature = "async")]
+mod async;
 
 pub use self::sampleformat::{ SampleFormat, SampleFormatType };
 pub use self::resample::Resample;
 pub use self::audiodecoder::AudioDecoder;
+pub use self::subtitlereader::{ SubtitleReader, SubtitleCue };
+#[cfg(feature = "async")]
+pub use self::async::{ ThreadedDecoder, StreamParameters, FrameStream, PacketStream, Opening };
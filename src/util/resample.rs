@@ -13,6 +13,8 @@ pub struct Resample<I = i16, O = i16> {
     _marker: PhantomData<(I, O)>,
 }
 
+unsafe impl<I, O> Send for Resample<I, O> {}
+
 impl<I: SampleFormatType, O: SampleFormatType + Clone + Default> Resample<I, O> {
     pub fn new(in_channels: usize, out_channels: usize, in_sample_rate: usize, out_sample_rate: usize) -> Self {
         unsafe {
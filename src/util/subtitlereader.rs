@@ -0,0 +1,144 @@
+use std::old_io::Reader;
+use libc::c_int;
+
+use avformat::{ AvFormatContext, AvioContext, AvioContextReader };
+use avcodec::{ AvCodecContext, AvPacket, SubtitleRect };
+use avutil::AvDictionary;
+use ffi::{ self, AVRational, AVMEDIA_TYPE_SUBTITLE, AV_NOPTS_VALUE, AV_TIME_BASE };
+
+const MILLISECONDS: AVRational = AVRational { num: 1, den: 1000 };
+const AV_TIME_BASE_Q: AVRational = AVRational { num: 1, den: AV_TIME_BASE };
+
+/// A subtitle event, timed in milliseconds from the start of the input.
+pub struct SubtitleCue {
+    pub start: i64,
+    /// `None` if the subtitle stays up until the next cue replaces it.
+    pub end: Option<i64>,
+    pub rects: Vec<SubtitleRect>,
+}
+
+/// Iterates over the cues of a subtitle track, from either a standalone subtitle file (SRT, ASS,
+/// WebVTT, ...) or a track embedded in a container such as MKV or MP4.
+pub struct SubtitleReader<T> {
+    context: AvFormatContext<T>,
+    decoder: AvCodecContext<'static>,
+    stream_index: usize,
+    time_base: AVRational,
+    packet: AvPacket<'static>,
+    finished: bool,
+}
+
+impl<R: Reader> SubtitleReader<AvioContext<AvioContextReader<R>>> {
+    pub fn new(reader: R) -> Result<Self, c_int> {
+        let avio = AvioContext::new(0x1000, false, AvioContextReader::new(reader));
+        let (context, _) = AvFormatContext::open_input(avio, AvDictionary::new());
+        SubtitleReader::from_context(try!(context), None)
+    }
+}
+
+impl SubtitleReader<()> {
+    pub fn open_file(filename: &str) -> Result<Self, c_int> {
+        let (context, _) = AvFormatContext::open_file(filename, AvDictionary::new());
+        SubtitleReader::from_context(try!(context), None)
+    }
+}
+
+impl<T> SubtitleReader<T> {
+    /// Reads the given stream, or the first subtitle stream if `stream_index` is `None`.
+    pub fn from_context(context: AvFormatContext<T>, stream_index: Option<usize>) -> Result<Self, c_int> {
+        let (res, _) = context.find_stream_info(AvDictionary::new());
+        try!(res);
+
+        let stream_index = match stream_index {
+            Some(index) => index,
+            None => try!(context.find_stream(AVMEDIA_TYPE_SUBTITLE).ok_or(-1)),
+        };
+        // An owned copy, so that the decoder doesn't borrow the context stored alongside it.
+        let (decoder, _) = context.open_stream_copy(stream_index, AvDictionary::new());
+        let decoder = try!(decoder);
+
+        // Lets the decoder fill in `AVSubtitle.pts`.
+        let time_base = context.stream_time_base(stream_index);
+        decoder.set_pkt_timebase(&time_base);
+
+        Ok(SubtitleReader {
+            context: context,
+            decoder: decoder,
+            stream_index: stream_index,
+            time_base: time_base,
+            packet: AvPacket::empty(),
+            finished: false,
+        })
+    }
+
+    pub fn stream_index(&self) -> usize {
+        self.stream_index
+    }
+
+    fn decode_packet(&mut self) -> Result<Option<SubtitleCue>, c_int> {
+        let subtitle = match try!(self.decoder.decode_subtitle(&mut self.packet)) {
+            Some(subtitle) => subtitle,
+            None => return Ok(None),
+        };
+
+        let base = if subtitle.pts() != AV_NOPTS_VALUE {
+            unsafe { ffi::av_rescale_q(subtitle.pts(), AV_TIME_BASE_Q, MILLISECONDS) }
+        } else if self.packet.pts() != AV_NOPTS_VALUE {
+            unsafe { ffi::av_rescale_q(self.packet.pts(), self.time_base, MILLISECONDS) }
+        } else {
+            0
+        };
+
+        let end = if subtitle.end_display_time() > subtitle.start_display_time() {
+            Some(base + subtitle.end_display_time() as i64)
+        } else if self.packet.duration() > 0 {
+            Some(base + unsafe { ffi::av_rescale_q(self.packet.duration() as i64, self.time_base, MILLISECONDS) })
+        } else {
+            None
+        };
+
+        Ok(Some(SubtitleCue {
+            start: base + subtitle.start_display_time() as i64,
+            end: end,
+            rects: subtitle.rects(),
+        }))
+    }
+}
+
+/// Yields each cue, or the error from a packet that failed to decode. Iteration carries on past
+/// decode errors, and ends with the input or after yielding a read error.
+impl<T> Iterator for SubtitleReader<T> {
+    type Item = Result<SubtitleCue, c_int>;
+
+    fn next(&mut self) -> Option<Result<SubtitleCue, c_int>> {
+        if self.finished {
+            return None
+        }
+        loop {
+            self.packet.clear();
+            match self.context.read_packet(&mut self.packet) {
+                Ok(()) => (),
+                Err(error) => {
+                    self.finished = true;
+                    return if error == ffi::AVERROR_EOF { None } else { Some(Err(error)) }
+                }
+            }
+            if self.packet.stream_index() != self.stream_index {
+                continue
+            }
+
+            match self.decode_packet() {
+                Ok(Some(cue)) => return Some(Ok(cue)),
+                Ok(None) => continue,
+                Err(error) => return Some(Err(error)),
+            }
+        }
+    }
+}
+
+#[unsafe_destructor]
+impl<T> Drop for SubtitleReader<T> {
+    fn drop(&mut self) {
+        self.packet.clear();
+    }
+}
@@ -0,0 +1,86 @@
+#![cfg(feature = "async")]
+#![feature(collections, old_io)]
+
+extern crate ffmpeg;
+extern crate futures;
+
+use std::old_io::MemReader;
+
+use futures::executor::block_on;
+use futures::io::Cursor;
+use futures::stream::StreamExt;
+
+use ffmpeg::avformat;
+use ffmpeg::ffi::{ AVMEDIA_TYPE_AUDIO, AVMEDIA_TYPE_VIDEO };
+use ffmpeg::util::{ FrameStream, PacketStream, ThreadedDecoder };
+
+mod common;
+
+#[test]
+fn threaded_decoder_decodes_every_sample() {
+    avformat::init();
+
+    let decoder = ThreadedDecoder::spawn_seekable(MemReader::new(common::wav()), AVMEDIA_TYPE_AUDIO, 2).unwrap();
+    assert_eq!(decoder.parameters().sample_rate as u32, common::SAMPLE_RATE);
+    let samples = decoder.fold(0, |samples, frame| samples + frame.unwrap().sample_count() as usize);
+    assert_eq!(samples, common::SAMPLES);
+}
+
+#[test]
+fn threaded_decoder_reports_missing_streams() {
+    avformat::init();
+
+    assert!(ThreadedDecoder::spawn(MemReader::new(common::wav()), AVMEDIA_TYPE_VIDEO, 2).is_err());
+}
+
+#[test]
+fn frame_stream_decodes_every_sample() {
+    avformat::init();
+
+    // No room for even one frame, so the worker waits on every send.
+    let stream = block_on(FrameStream::open(Cursor::new(common::wav()), AVMEDIA_TYPE_AUDIO, 0)).unwrap();
+    assert_eq!(stream.parameters().channels, 1);
+    let frames: Vec<_> = block_on(stream.collect());
+    let samples = frames.into_iter().fold(0, |samples, frame| samples + frame.unwrap().sample_count() as usize);
+    assert_eq!(samples, common::SAMPLES);
+}
+
+#[test]
+fn packet_stream_demuxes_every_byte() {
+    avformat::init();
+
+    let stream = block_on(PacketStream::open(Cursor::new(common::wav()), 1)).unwrap();
+    assert_eq!(stream.streams().len(), 1);
+    assert_eq!(stream.streams()[0].media_type, AVMEDIA_TYPE_AUDIO);
+    assert_eq!(stream.streams()[0].sample_rate as u32, common::SAMPLE_RATE);
+
+    let packets: Vec<_> = block_on(stream.collect());
+    let mut bytes = 0;
+    for packet in packets.into_iter() {
+        let mut packet = packet.unwrap();
+        assert_eq!(packet.stream_index(), 0);
+        bytes += packet.data().len();
+        packet.clear();
+    }
+    // 16-bit mono samples.
+    assert_eq!(bytes, common::SAMPLES * 2);
+}
+
+#[test]
+fn dropping_a_stream_stops_its_worker() {
+    avformat::init();
+
+    let mut stream = block_on(PacketStream::open(Cursor::new(common::wav()), 1)).unwrap();
+    let mut packet = block_on(stream.next()).unwrap().unwrap();
+    packet.clear();
+    drop(stream);
+}
+
+#[test]
+fn opening_garbage_fails() {
+    avformat::init();
+
+    let garbage = vec![0x55u8; 4096];
+    assert!(block_on(FrameStream::open(Cursor::new(garbage.clone()), AVMEDIA_TYPE_AUDIO, 1)).is_err());
+    assert!(block_on(PacketStream::open(Cursor::new(garbage), 1)).is_err());
+}
@@ -0,0 +1,72 @@
+extern crate ffmpeg;
+
+use ffmpeg::avcodec::CodecId;
+use ffmpeg::avformat::{ self, AttachedPicture };
+
+/// A 2x2 24-bit BMP: blue, green on the bottom row and red, white on the top row.
+fn bmp() -> Vec<u8> {
+    let mut data = Vec::new();
+    let le32 = |data: &mut Vec<u8>, value: u32| {
+        for i in 0..4 {
+            data.push((value >> (i * 8)) as u8);
+        }
+    };
+
+    // File header.
+    data.push(b'B');
+    data.push(b'M');
+    le32(&mut data, 70);
+    le32(&mut data, 0);
+    le32(&mut data, 54);
+    // Info header: 2x2, one plane, 24 bits per pixel, uncompressed.
+    le32(&mut data, 40);
+    le32(&mut data, 2);
+    le32(&mut data, 2);
+    le32(&mut data, 1 | (24 << 16));
+    le32(&mut data, 0);
+    le32(&mut data, 16);
+    le32(&mut data, 2835);
+    le32(&mut data, 2835);
+    le32(&mut data, 0);
+    le32(&mut data, 0);
+    // Rows are stored bottom up as BGR, padded to four bytes.
+    for &pixel in [[255, 0, 0], [0, 255, 0], [0, 0, 255], [255, 255, 255]].iter() {
+        for &component in pixel.iter() {
+            data.push(component);
+        }
+        if data.len() == 60 || data.len() == 68 {
+            data.push(0);
+            data.push(0);
+        }
+    }
+    data
+}
+
+#[test]
+fn decoded_picture_outlives_its_decoder() {
+    avformat::init();
+
+    let picture = AttachedPicture {
+        stream_index: 0,
+        codec_id: CodecId::Bmp.id().unwrap(),
+        data: bmp(),
+    };
+    assert_eq!(picture.mime_type(), Some("image/bmp"));
+
+    let frame = picture.decode().unwrap();
+    assert_eq!((frame.width(), frame.height()), (2, 2));
+    // The top row, stored last, comes out first: red then white.
+    assert_eq!(&frame.video_data(0)[..6], &[0, 0, 255, 255, 255, 255]);
+}
+
+#[test]
+fn reports_data_that_decodes_to_nothing() {
+    avformat::init();
+
+    let picture = AttachedPicture {
+        stream_index: 0,
+        codec_id: CodecId::Bmp.id().unwrap(),
+        data: b"not a picture".to_vec(),
+    };
+    assert!(picture.decode().is_err());
+}
@@ -0,0 +1,91 @@
+#![feature(collections, libc)]
+
+extern crate ffmpeg;
+extern crate libc;
+
+use std::env;
+use std::fs;
+
+use ffmpeg::avformat::{ self, Avio };
+use ffmpeg::avutil::AvDictionary;
+use ffmpeg::ffi::{ AVIO_FLAG_READ, AVIO_FLAG_WRITE };
+
+const DATA: &'static [u8] = b"Round trip through FFmpeg's protocols";
+
+fn read_all(avio: &mut Avio) -> Vec<u8> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 16];
+    loop {
+        match avio.read(&mut buf).unwrap() {
+            0 => return data,
+            read => data.push_all(&buf[..read]),
+        }
+    }
+}
+
+fn temp_path(name: &str) -> String {
+    env::temp_dir().join(name).to_str().unwrap().to_string()
+}
+
+#[test]
+fn file_round_trip() {
+    avformat::init();
+    let path = temp_path("ffmpeg-rs-avio-file");
+
+    {
+        let (avio, _) = Avio::open(&format!("file:{}", path), AVIO_FLAG_WRITE, AvDictionary::new());
+        let mut avio = avio.unwrap();
+        avio.write(DATA).unwrap();
+        avio.flush().unwrap();
+    }
+
+    let (avio, _) = Avio::open(&format!("file:{}", path), AVIO_FLAG_READ, AvDictionary::new());
+    let mut avio = avio.unwrap();
+    assert_eq!(avio.size(), Ok(DATA.len() as u64));
+    assert_eq!(read_all(&mut avio), DATA);
+
+    drop(avio);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn pipe_round_trip() {
+    avformat::init();
+    let mut fds = [0 as libc::c_int; 2];
+    assert_eq!(unsafe { libc::pipe(fds.as_mut_ptr()) }, 0);
+
+    // The pipe protocol doesn't close its descriptor, so both ends are closed here.
+    {
+        let (avio, _) = Avio::open(&format!("pipe:{}", fds[1]), AVIO_FLAG_WRITE, AvDictionary::new());
+        let mut avio = avio.unwrap();
+        assert!(!avio.is_seekable());
+        avio.write(DATA).unwrap();
+        avio.flush().unwrap();
+    }
+    unsafe { libc::close(fds[1]); }
+
+    let (avio, _) = Avio::open(&format!("pipe:{}", fds[0]), AVIO_FLAG_READ, AvDictionary::new());
+    let mut avio = avio.unwrap();
+    assert_eq!(read_all(&mut avio), DATA);
+
+    drop(avio);
+    unsafe { libc::close(fds[0]); }
+}
+
+#[test]
+fn protocol_whitelist_is_enforced_or_reported() {
+    avformat::init();
+    let path = temp_path("ffmpeg-rs-avio-whitelist");
+    fs::File::create(&path).unwrap();
+
+    let mut options = AvDictionary::new();
+    avformat::set_protocol_whitelist(&mut options, &["pipe"]);
+    let (avio, options_not_found) = Avio::open(&format!("file:{}", path), AVIO_FLAG_READ, options);
+    match avformat::unapplied_restriction(&options_not_found) {
+        Some(option) => assert_eq!(option, "protocol_whitelist"),
+        None => assert!(avio.is_err()),
+    }
+
+    drop(avio);
+    fs::remove_file(&path).unwrap();
+}
@@ -0,0 +1,65 @@
+extern crate ffmpeg;
+
+use ffmpeg::avcodec::{ AvBitstreamFilter, AvCodec, AvCodecContext, AvPacket, CodecId };
+use ffmpeg::avformat;
+use ffmpeg::avutil::SideData;
+use ffmpeg::ffi::{ self, AVERROR_EAGAIN, AVERROR_EOF, AV_PKT_DATA_SKIP_SAMPLES, AV_PKT_FLAG_KEY };
+
+fn padded(data: &[u8]) -> Vec<u8> {
+    let mut padded = data.to_vec();
+    for _ in 0..ffi::FF_INPUT_BUFFER_PADDING_SIZE {
+        padded.push(0);
+    }
+    padded
+}
+
+#[test]
+fn filters_packets_keeping_their_properties() {
+    avformat::init();
+
+    let codec = AvCodec::find_decoder(CodecId::PcmS16le.id().unwrap()).unwrap();
+    let mut context = AvCodecContext::new(&codec);
+    context.set_extra_data(b"head");
+    // `dump_extra` prepends the codec's extradata to key frames and passes other packets through.
+    let mut filter = AvBitstreamFilter::new("dump_extra", &context).unwrap();
+
+    let mut data = padded(b"body");
+    let mut packet = AvPacket::new(&mut data);
+    packet.set_pts(5);
+    packet.set_stream_index(1);
+    packet.set_flags(AV_PKT_FLAG_KEY);
+    unsafe {
+        let side_data = ffi::av_packet_new_side_data(packet.packet.ptr(), AV_PKT_DATA_SKIP_SAMPLES, 10);
+        for i in 0..10 {
+            *side_data.offset(i) = 0;
+        }
+        *side_data = 3;
+    }
+
+    filter.send_packet(Some(&packet)).unwrap();
+    assert_eq!(filter.send_packet(Some(&packet)).err(), Some(AVERROR_EAGAIN));
+    let mut output = filter.receive_packet().unwrap();
+    assert_eq!(output.data(), &b"headbody"[..]);
+    assert_eq!((output.pts(), output.stream_index()), (5, 1));
+    assert!(output.is_key_frame());
+    let side_data = output.side_data();
+    assert_eq!(side_data.len(), 1);
+    match side_data[0] {
+        SideData::SkipSamples { start, end } => assert_eq!((start, end), (3, 0)),
+        _ => panic!("expected the input's side data"),
+    }
+    output.clear();
+    assert_eq!(filter.receive_packet().err(), Some(AVERROR_EAGAIN));
+
+    packet.set_flags(0);
+    filter.send_packet(Some(&packet)).unwrap();
+    let mut output = filter.receive_packet().unwrap();
+    assert_eq!(output.data(), &b"body"[..]);
+    assert!(!output.is_key_frame());
+    output.clear();
+    packet.clear();
+
+    filter.send_packet(None).unwrap();
+    assert_eq!(filter.receive_packet().err(), Some(AVERROR_EOF));
+    assert_eq!(filter.send_packet(None).err(), Some(AVERROR_EOF));
+}
@@ -0,0 +1,50 @@
+#![feature(collections)]
+
+extern crate ffmpeg;
+
+use ffmpeg::avformat::{ self, AvFormatContext, AvioContext };
+use ffmpeg::avutil::AvDictionary;
+use ffmpeg::ffi::AVRational;
+
+mod common;
+
+#[test]
+fn writes_and_reads_back_chapters() {
+    avformat::init();
+    let milliseconds = AVRational { num: 1, den: 1000 };
+
+    // `ffmetadata` keeps chapters without needing any streams.
+    let mut output = AvFormatContext::open_output(AvioContext::memory_writer(), "ffmetadata").unwrap();
+    output.add_chapter(1, milliseconds, 0, 1500, Some("Intro")).unwrap();
+    output.add_chapter(2, milliseconds, 1500, 3000, None).unwrap();
+    let (result, _) = output.write_header(AvDictionary::new());
+    result.unwrap();
+    output.write_trailer().unwrap();
+    let data = output.into_io().unwrap().into_inner().into_inner();
+
+    let (input, _) = AvFormatContext::open_input(AvioContext::from_bytes(data), AvDictionary::new());
+    let input = input.unwrap();
+    let chapters: Vec<_> = input.chapters().collect();
+    assert_eq!(chapters.len(), 2);
+
+    let time_base = chapters[0].time_base();
+    assert_eq!((time_base.num, time_base.den), (1, 1000));
+    assert_eq!((chapters[0].start(), chapters[0].end()), (0, 1500));
+    assert_eq!(chapters[0].title(), Some("Intro".to_string()));
+    assert_eq!((chapters[1].start(), chapters[1].end()), (1500, 3000));
+    assert_eq!(chapters[1].title(), None);
+}
+
+#[test]
+fn input_has_no_chapters_or_programs_to_add_to() {
+    avformat::init();
+
+    let (input, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+    let mut input = input.unwrap();
+    assert_eq!(input.chapters().count(), 0);
+    assert_eq!(input.programs().count(), 0);
+
+    let milliseconds = AVRational { num: 1, den: 1000 };
+    assert!(input.add_chapter(1, milliseconds, 0, 1000, None).is_err());
+    assert_eq!(input.chapters().count(), 0);
+}
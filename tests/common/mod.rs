@@ -0,0 +1,40 @@
+//! Inputs shared by the integration tests, built in memory so that no media files are needed.
+
+pub const SAMPLE_RATE: u32 = 8000;
+pub const SAMPLES: usize = 4000;
+
+fn push_u16(data: &mut Vec<u8>, value: u16) {
+    data.push(value as u8);
+    data.push((value >> 8) as u8);
+}
+
+fn push_u32(data: &mut Vec<u8>, value: u32) {
+    push_u16(data, value as u16);
+    push_u16(data, (value >> 16) as u16);
+}
+
+/// A mono 16-bit PCM WAV file holding half a second of a ramp.
+pub fn wav() -> Vec<u8> {
+    let data_size = (SAMPLES * 2) as u32;
+
+    let mut wav = Vec::new();
+    wav.push_all(b"RIFF");
+    push_u32(&mut wav, 36 + data_size);
+    wav.push_all(b"WAVE");
+
+    wav.push_all(b"fmt ");
+    push_u32(&mut wav, 16);
+    push_u16(&mut wav, 1);
+    push_u16(&mut wav, 1);
+    push_u32(&mut wav, SAMPLE_RATE);
+    push_u32(&mut wav, SAMPLE_RATE * 2);
+    push_u16(&mut wav, 2);
+    push_u16(&mut wav, 16);
+
+    wav.push_all(b"data");
+    push_u32(&mut wav, data_size);
+    for i in 0..SAMPLES {
+        push_u16(&mut wav, (i * 16) as u16);
+    }
+    wav
+}
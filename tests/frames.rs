@@ -0,0 +1,66 @@
+#![feature(collections)]
+
+extern crate ffmpeg;
+
+use ffmpeg::avcodec::AvPacket;
+use ffmpeg::avformat::{ self, AvFormatContext, AvioContext };
+use ffmpeg::avutil::{ AvDictionary, AvFrame, ColorRange, ColorSpace, PictureType };
+use ffmpeg::ffi::{ self, AVMEDIA_TYPE_AUDIO };
+
+mod common;
+
+#[test]
+fn reads_back_color_properties() {
+    let frame = AvFrame::new();
+    assert_eq!(frame.color_space(), Some(ColorSpace::Unspecified));
+    assert_eq!(frame.color_range(), Some(ColorRange::Unspecified));
+
+    unsafe {
+        ffi::av_frame_set_colorspace(frame.frame, ColorSpace::Bt709.as_raw());
+        ffi::av_frame_set_color_range(frame.frame, ColorRange::Jpeg.as_raw());
+    }
+    assert_eq!(frame.color_space(), Some(ColorSpace::Bt709));
+    assert_eq!(frame.color_range(), Some(ColorRange::Jpeg));
+
+    // Values this crate doesn't know about are reported as such rather than misread.
+    unsafe {
+        ffi::av_frame_set_colorspace(frame.frame, 3);
+    }
+    assert_eq!(frame.color_space(), None);
+}
+
+#[test]
+fn converts_enums_to_and_from_raw() {
+    for space in [ColorSpace::Rgb, ColorSpace::Bt709, ColorSpace::Bt2020Ncl].iter() {
+        assert_eq!(ColorSpace::from_raw(space.as_raw()), Some(*space));
+    }
+    for range in [ColorRange::Unspecified, ColorRange::Mpeg, ColorRange::Jpeg].iter() {
+        assert_eq!(ColorRange::from_raw(range.as_raw()), Some(*range));
+    }
+    assert_eq!(PictureType::from_raw(PictureType::I.as_raw()), Some(PictureType::I));
+    assert_eq!(ColorRange::from_raw(-1), None);
+}
+
+#[test]
+fn decoded_audio_frames_are_intact_key_frames() {
+    avformat::init();
+
+    let (input, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+    let input = input.unwrap();
+    let (result, _) = input.find_stream_info(AvDictionary::new());
+    result.unwrap();
+    let index = input.find_stream(AVMEDIA_TYPE_AUDIO).unwrap();
+    let (decoder, _) = input.open_stream(index, AvDictionary::new());
+    let decoder = decoder.unwrap();
+
+    let mut packet = AvPacket::empty();
+    input.read_packet(&mut packet).unwrap();
+    let frame = AvFrame::new();
+    decoder.decode_audio(&frame, &mut packet).unwrap();
+
+    assert!(frame.sample_count() > 0);
+    assert!(frame.is_key_frame());
+    assert!(!frame.is_corrupt());
+    assert_eq!(frame.picture_type(), Some(PictureType::None));
+    assert!(frame.pkt_pos() >= 0);
+}
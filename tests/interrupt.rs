@@ -0,0 +1,48 @@
+#![feature(collections)]
+
+extern crate ffmpeg;
+
+use ffmpeg::avformat::{ self, AvFormatContext, AvioContext, CancelToken, InterruptCallback, ERROR_INTERRUPTED };
+use ffmpeg::avutil::AvDictionary;
+
+mod common;
+
+#[test]
+fn cancelling_interrupts_find_stream_info() {
+    avformat::init();
+
+    let (context, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+    let mut context = context.unwrap();
+    let cancel = CancelToken::new();
+    context.set_interrupt_callback(cancel.interrupt_callback());
+
+    cancel.cancel();
+    let (result, _) = context.find_stream_info(AvDictionary::new());
+    assert_eq!(result, Err(ERROR_INTERRUPTED));
+}
+
+#[test]
+fn uncancelled_token_lets_calls_finish() {
+    avformat::init();
+
+    let (context, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+    let mut context = context.unwrap();
+    let cancel = CancelToken::new();
+    context.set_interrupt_callback(cancel.interrupt_callback_with_timeout(60000000));
+
+    let (result, _) = context.find_stream_info(AvDictionary::new());
+    assert_eq!(result, Ok(()));
+    assert!(!cancel.is_cancelled());
+}
+
+#[test]
+fn expired_timeout_interrupts() {
+    avformat::init();
+
+    let (context, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+    let mut context = context.unwrap();
+    context.set_interrupt_callback(InterruptCallback::timeout(0));
+
+    let (result, _) = context.find_stream_info(AvDictionary::new());
+    assert_eq!(result, Err(ERROR_INTERRUPTED));
+}
@@ -0,0 +1,75 @@
+#![feature(collections)]
+
+extern crate ffmpeg;
+
+use ffmpeg::avformat::{ self, AvFormatContext, AvioContext, AvioContextCallbacks, AvioMemoryReader, AvioMemoryWriter };
+use ffmpeg::avcodec::AvPacket;
+use ffmpeg::avutil::{ AvDictionary, AvFrame };
+use ffmpeg::ffi::{ AVMEDIA_TYPE_AUDIO, AVSEEK_SIZE, SEEK_CUR, SEEK_END, SEEK_SET };
+
+mod common;
+
+#[test]
+fn memory_writer_seeks_and_fills_gaps() {
+    let mut writer = AvioMemoryWriter::new();
+    assert_eq!(writer.write(b"abcdef").unwrap(), 6);
+    assert_eq!(writer.seek(2, SEEK_SET).unwrap(), 2);
+    writer.write(b"XY").unwrap();
+    assert_eq!(writer.seek(2, SEEK_END).unwrap(), 8);
+    writer.write(b"!").unwrap();
+    assert_eq!(writer.seek(0, AVSEEK_SIZE).unwrap(), 9);
+    assert!(writer.seek(-10, SEEK_CUR).is_err());
+
+    assert_eq!(writer.into_inner(), b"abXYef\0\0!".to_vec());
+}
+
+#[test]
+fn memory_reader_reads_what_was_written() {
+    let mut writer = AvioMemoryWriter::new();
+    writer.write(b"0123456789").unwrap();
+    let mut reader = AvioMemoryReader::new(writer.into_inner());
+
+    let mut buf = [0u8; 4];
+    assert_eq!(reader.read(&mut buf).unwrap(), 4);
+    assert_eq!(&buf, b"0123");
+    assert_eq!(reader.seek(-2, SEEK_END).unwrap(), 8);
+    assert_eq!(reader.read(&mut buf).unwrap(), 2);
+    assert_eq!(&buf[..2], b"89");
+    assert_eq!(reader.read(&mut buf).unwrap(), 0);
+    assert_eq!(reader.seek(0, AVSEEK_SIZE).unwrap(), 10);
+}
+
+#[test]
+fn demuxes_what_was_written_to_memory() {
+    avformat::init();
+
+    let mut writer = AvioMemoryWriter::new();
+    let wav = common::wav();
+    for chunk in wav.chunks(1000) {
+        writer.write(chunk).unwrap();
+    }
+    let data = writer.into_inner();
+    assert_eq!(data, wav);
+
+    let (input, _) = AvFormatContext::open_input(AvioContext::from_bytes(&data[..]), AvDictionary::new());
+    let input = input.unwrap();
+    let (result, _) = input.find_stream_info(AvDictionary::new());
+    result.unwrap();
+    let index = input.find_stream(AVMEDIA_TYPE_AUDIO).unwrap();
+    let (decoder, _) = input.open_stream(index, AvDictionary::new());
+    let decoder = decoder.unwrap();
+    assert_eq!(decoder.sample_rate() as u32, common::SAMPLE_RATE);
+
+    // Reading on to the end decodes every sample.
+    let mut packet = AvPacket::empty();
+    let mut samples = 0;
+    while input.read_packet(&mut packet).is_ok() {
+        while packet.has_data() {
+            let frame = AvFrame::new();
+            decoder.decode_audio(&frame, &mut packet).unwrap();
+            samples += frame.sample_count() as usize;
+        }
+        packet.clear();
+    }
+    assert_eq!(samples, common::SAMPLES);
+}
@@ -0,0 +1,136 @@
+#![feature(collections)]
+
+extern crate ffmpeg;
+
+use std::env;
+use std::fs;
+use std::io::Write;
+
+use ffmpeg::avformat::{ self, AvFormatContext, AvInputFormat, AvioContext, InterruptCallback, OpenOptions,
+                        SafeOpenError, SafeOpenPolicy };
+use ffmpeg::avutil::{ AvDictionary, AvFrame };
+use ffmpeg::ffi::AVMEDIA_TYPE_AUDIO;
+
+mod common;
+
+#[test]
+fn opens_with_format_and_interrupt() {
+    avformat::init();
+
+    let format = AvInputFormat::find("wav").unwrap();
+    let open_options = OpenOptions::new()
+        .format(&format)
+        .interrupt(InterruptCallback::new(|| false))
+        .probe_size(4096);
+    let (context, _) = AvFormatContext::open_input_with(AvioContext::from_bytes(common::wav()),
+                                                        open_options,
+                                                        AvDictionary::new());
+    let context = context.unwrap();
+    let (result, _) = context.find_stream_info(AvDictionary::new());
+    result.unwrap();
+    assert!(context.find_stream(AVMEDIA_TYPE_AUDIO).is_some());
+}
+
+#[test]
+fn policy_refuses_formats_before_opening() {
+    avformat::init();
+
+    let open_options = OpenOptions::new().policy(SafeOpenPolicy::new().formats(&["matroska"]));
+    let (context, _) = AvFormatContext::open_input_with(AvioContext::from_bytes(common::wav()),
+                                                        open_options,
+                                                        AvDictionary::new());
+    match context {
+        Err(SafeOpenError::FormatNotAllowed(name)) => assert_eq!(name, "wav"),
+        Err(error) => panic!("unexpected error {:?}", error),
+        Ok(_) => panic!("opened a format outside the policy"),
+    }
+}
+
+#[test]
+fn opens_with_the_single_purpose_functions() {
+    avformat::init();
+
+    let format = AvInputFormat::find("wav").unwrap();
+    let (context, _) = AvFormatContext::open_input_with_format(AvioContext::from_bytes(common::wav()),
+                                                               &format,
+                                                               AvDictionary::new());
+    assert!(context.is_ok());
+
+    let (context, _) = AvFormatContext::open_input_interruptible(AvioContext::from_bytes(common::wav()),
+                                                                 InterruptCallback::new(|| false),
+                                                                 AvDictionary::new());
+    assert!(context.is_ok());
+
+    let policy = SafeOpenPolicy::new().formats(&["matroska"]);
+    let (context, _) = AvFormatContext::open_input_safe(AvioContext::from_bytes(common::wav()),
+                                                        &policy,
+                                                        AvDictionary::new());
+    assert!(context.is_err());
+}
+
+#[test]
+fn policy_opens_allowed_formats() {
+    avformat::init();
+
+    let policy = SafeOpenPolicy::new().formats(&["wav"]).codecs(&["pcm_s16le"]).max_resolution(640, 480);
+    let (context, _) = AvFormatContext::open_input_with(AvioContext::from_bytes(common::wav()),
+                                                        OpenOptions::new().policy(policy),
+                                                        AvDictionary::new());
+    let context = context.unwrap();
+    // `find_stream_info` has already been run.
+    assert!(context.find_stream(AVMEDIA_TYPE_AUDIO).is_some());
+}
+
+#[test]
+fn policy_opens_local_files_itself() {
+    avformat::init();
+    let path = env::temp_dir().join("ffmpeg-rs-open-safe.wav").to_str().unwrap().to_string();
+    fs::File::create(&path).unwrap().write_all(&common::wav()).unwrap();
+
+    let policy = SafeOpenPolicy::new().formats(&["wav"]);
+    let (context, _) = AvFormatContext::open_file_safe(&path, &policy, AvDictionary::new());
+    assert!(context.unwrap().find_stream(AVMEDIA_TYPE_AUDIO).is_some());
+    fs::remove_file(&path).unwrap();
+
+    // Opened by name only, so a URL is just a file that doesn't exist.
+    let (context, _) = AvFormatContext::open_file_safe(&format!("file:{}", path), &policy, AvDictionary::new());
+    match context {
+        Err(SafeOpenError::Ffmpeg(_)) => {}
+        _ => panic!("expected the file not to be found"),
+    }
+}
+
+#[test]
+fn policy_refuses_mp4_unless_allowed() {
+    let mov = "mov,mp4,m4a,3gp,3g2,mj2";
+    match SafeOpenPolicy::new().check_format(mov) {
+        Err(SafeOpenError::ExternalReferences(name)) => assert_eq!(name, mov),
+        _ => panic!("expected mov to be refused"),
+    }
+    assert!(SafeOpenPolicy::new().allow_mov().check_format(mov).is_ok());
+    assert!(SafeOpenPolicy::new().allow_mov().formats(&["mp4"]).check_format(mov).is_ok());
+    assert!(SafeOpenPolicy::new().allow_mov().formats(&["wav"]).check_format(mov).is_err());
+    assert!(SafeOpenPolicy::new().allow_mov().check_format("hls,applehttp").is_err());
+}
+
+#[test]
+fn policy_checks_decoded_frames() {
+    let policy = SafeOpenPolicy::new().max_resolution(640, 480);
+    let frame = AvFrame::new();
+    unsafe {
+        (*frame.frame).width = 640;
+        (*frame.frame).height = 480;
+    }
+    assert!(policy.check_frame(0, &frame).is_ok());
+
+    unsafe {
+        (*frame.frame).height = 481;
+    }
+    match policy.check_frame(2, &frame) {
+        Err(SafeOpenError::ResolutionTooLarge { stream, width, height }) => {
+            assert_eq!((stream, width, height), (2, 640, 481))
+        }
+        _ => panic!("expected the frame to be refused"),
+    }
+    assert!(SafeOpenPolicy::new().check_frame(0, &frame).is_ok());
+}
@@ -0,0 +1,57 @@
+#![feature(collections)]
+
+extern crate ffmpeg;
+
+use ffmpeg::avformat::{ self, AvFormatContext, AvioContext };
+use ffmpeg::avutil::{ AvDictionary, OptionDefault, Options };
+use ffmpeg::ffi::AVMEDIA_TYPE_AUDIO;
+
+mod common;
+
+#[test]
+fn sets_and_reads_back_format_options() {
+    avformat::init();
+
+    let (context, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+    let context = context.unwrap();
+
+    context.opt_set_int("probesize", 12345, false).unwrap();
+    assert_eq!(context.opt_get_int("probesize", false), Ok(12345));
+    context.opt_set("probesize", "23456", false).unwrap();
+    assert_eq!(context.opt_get("probesize", false), Ok("23456".to_string()));
+
+    assert!(context.opt_set_int("no_such_option", 1, false).is_err());
+    assert!(context.opt_get("no_such_option", false).is_err());
+}
+
+#[test]
+fn sets_and_reads_back_codec_options() {
+    avformat::init();
+
+    let (context, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+    let context = context.unwrap();
+    let (result, _) = context.find_stream_info(AvDictionary::new());
+    result.unwrap();
+    let index = context.find_stream(AVMEDIA_TYPE_AUDIO).unwrap();
+    let (decoder, _) = context.open_stream_copy(index, AvDictionary::new());
+    let decoder = decoder.unwrap();
+
+    assert_eq!(decoder.opt_get_int("ar", false), Ok(common::SAMPLE_RATE as i64));
+    decoder.opt_set_int("refcounted_frames", 1, false).unwrap();
+    assert_eq!(decoder.opt_get_int("refcounted_frames", false), Ok(1));
+}
+
+#[test]
+fn lists_options_with_defaults() {
+    avformat::init();
+
+    let (context, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+    let context = context.unwrap();
+
+    let probesize = context.opt_iter().find(|option| option.name == "probesize").unwrap();
+    assert!(!probesize.is_constant());
+    match probesize.default {
+        OptionDefault::Int(default) => assert_eq!(default, 5000000),
+        default => panic!("unexpected default {:?}", default),
+    }
+}
@@ -0,0 +1,31 @@
+extern crate ffmpeg;
+
+use ffmpeg::avcodec::{ self, AvCodec, CodecId };
+use ffmpeg::avformat;
+use ffmpeg::ffi::AVMEDIA_TYPE_AUDIO;
+
+#[test]
+fn lists_codecs() {
+    avformat::init();
+
+    let pcm = CodecId::PcmS16le.id().unwrap();
+    let codecs: Vec<_> = avcodec::codecs().filter(|codec| codec.id() == pcm).collect();
+    assert!(codecs.iter().any(|codec| codec.is_decoder()));
+    assert!(codecs.iter().any(|codec| codec.is_encoder()));
+    assert!(codecs.iter().all(|codec| codec.media_type() == AVMEDIA_TYPE_AUDIO));
+
+    let decoder = AvCodec::find_decoder_by_name("pcm_s16le").unwrap();
+    assert_eq!(decoder.id(), pcm);
+    assert_eq!(CodecId::from_id(decoder.id()), Some(CodecId::PcmS16le));
+}
+
+#[test]
+fn lists_demuxers_and_muxers() {
+    avformat::init();
+
+    assert!(avformat::demuxers().any(|format| format.name() == "wav"));
+
+    let muxer = avformat::muxers().find(|format| format.name() == "wav").unwrap();
+    assert!(muxer.extensions().iter().any(|extension| *extension == "wav"));
+    assert_eq!(muxer.default_audio_codec(), CodecId::PcmS16le.id().unwrap());
+}
@@ -0,0 +1,73 @@
+extern crate ffmpeg;
+
+use std::ptr;
+
+use ffmpeg::avutil::{ AvFrame, SideData };
+use ffmpeg::ffi::{ self, AV_FRAME_DATA_A53_CC, AV_FRAME_DATA_DISPLAYMATRIX, AV_FRAME_DATA_SKIP_SAMPLES,
+                   AV_PKT_DATA_SKIP_SAMPLES };
+
+/// A display matrix rotating by 90 degrees counterclockwise, in 16.16 and 2.30 fixed point.
+const ROTATE_90: [i32; 9] = [0, -0x10000, 0, 0x10000, 0, 0, 0, 0, 0x40000000];
+
+fn add_frame_side_data(frame: &AvFrame, kind: ffi::AVFrameSideDataType, data: &[u8]) {
+    unsafe {
+        let side_data = ffi::av_frame_new_side_data(frame.frame, kind, data.len() as i32);
+        assert!(!side_data.is_null());
+        ptr::copy_nonoverlapping(data.as_ptr(), (*side_data).data, data.len());
+    }
+}
+
+fn as_bytes(matrix: &[i32; 9]) -> &[u8] {
+    unsafe {
+        std::slice::from_raw_parts(matrix.as_ptr() as *const u8, 36)
+    }
+}
+
+#[test]
+fn reads_back_frame_side_data() {
+    let frame = AvFrame::new();
+    assert!(frame.side_data().is_empty());
+
+    add_frame_side_data(&frame, AV_FRAME_DATA_DISPLAYMATRIX, as_bytes(&ROTATE_90));
+    add_frame_side_data(&frame, AV_FRAME_DATA_SKIP_SAMPLES, &[100, 0, 0, 0, 5, 0, 0, 0, 0, 0]);
+    add_frame_side_data(&frame, AV_FRAME_DATA_A53_CC, b"cc");
+
+    let side_data = frame.side_data();
+    assert_eq!(side_data.len(), 3);
+    match side_data[0] {
+        SideData::DisplayMatrix(ref matrix) => assert_eq!(matrix.rotation(), 90.0),
+        _ => panic!("expected a display matrix"),
+    }
+    match side_data[1] {
+        SideData::SkipSamples { start, end } => assert_eq!((start, end), (100, 5)),
+        _ => panic!("expected skip samples"),
+    }
+    match side_data[2] {
+        SideData::A53ClosedCaptions(ref data) => assert_eq!(data, b"cc"),
+        _ => panic!("expected closed captions"),
+    }
+}
+
+#[test]
+fn keeps_truncated_side_data_as_is() {
+    let frame = AvFrame::new();
+    add_frame_side_data(&frame, AV_FRAME_DATA_DISPLAYMATRIX, &[1, 2, 3]);
+
+    match frame.side_data()[0] {
+        SideData::OtherFrameData(kind, ref data) => {
+            assert_eq!(kind, AV_FRAME_DATA_DISPLAYMATRIX);
+            assert_eq!(data, &[1, 2, 3]);
+        }
+        _ => panic!("expected undecoded side data"),
+    }
+}
+
+#[test]
+fn decodes_packet_side_data() {
+    let bytes = [7u8, 0, 0, 0, 1, 1, 0, 0, 0, 0];
+    match unsafe { SideData::from_packet_raw(AV_PKT_DATA_SKIP_SAMPLES, bytes.as_ptr(), bytes.len() as i32) } {
+        SideData::SkipSamples { start, end } => assert_eq!((start, end), (7, 257)),
+        _ => panic!("expected skip samples"),
+    }
+    assert!(unsafe { SideData::from_packet_list(ptr::null(), 0) }.is_empty());
+}
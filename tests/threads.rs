@@ -0,0 +1,99 @@
+#![feature(collections)]
+
+extern crate ffmpeg;
+
+use std::sync::mpsc::channel;
+use std::thread;
+
+use ffmpeg::avformat::{ self, AvFormatContext, AvioContext };
+use ffmpeg::avcodec::AvPacket;
+use ffmpeg::avutil::{ AvDictionary, AvFrame };
+use ffmpeg::ffi::AVMEDIA_TYPE_AUDIO;
+
+mod common;
+
+#[test]
+fn decodes_on_another_thread() {
+    avformat::init();
+
+    let (sender, frames) = channel();
+    let worker = thread::spawn(move || {
+        let (context, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+        let context = context.unwrap();
+        let (result, _) = context.find_stream_info(AvDictionary::new());
+        result.unwrap();
+        let index = context.find_stream(AVMEDIA_TYPE_AUDIO).unwrap();
+
+        // Frames must own their data to outlive the decoder.
+        let mut options = AvDictionary::new();
+        options.set("refcounted_frames", "1");
+        let (decoder, _) = context.open_stream_copy(index, options);
+        let decoder = decoder.unwrap();
+
+        let mut packet = AvPacket::empty();
+        let mut count = 0usize;
+        while context.read_packet(&mut packet).is_ok() {
+            while packet.has_data() {
+                let mut frame = AvFrame::new();
+                decoder.decode_audio(&frame, &mut packet).unwrap();
+                assert!(frame.is_refcounted());
+                frame.set_user_data(Box::new(count));
+                count += 1;
+                sender.send(frame).unwrap();
+            }
+            packet.clear();
+        }
+
+        // The decoder owns its context, so it can be handed back too.
+        decoder
+    });
+
+    let decoder = worker.join().unwrap();
+    assert_eq!(decoder.sample_rate() as u32, common::SAMPLE_RATE);
+    assert_eq!(decoder.channels(), 1);
+
+    let mut samples = 0;
+    for (i, frame) in frames.iter().enumerate() {
+        assert_eq!(frame.user_data().downcast_ref::<usize>(), Some(&i));
+        samples += frame.sample_count() as usize;
+    }
+    assert_eq!(samples, common::SAMPLES);
+}
+
+#[test]
+fn hands_packets_to_another_thread() {
+    avformat::init();
+
+    let (context, _) = AvFormatContext::open_input(AvioContext::from_bytes(common::wav()), AvDictionary::new());
+    let context = context.unwrap();
+    let (result, _) = context.find_stream_info(AvDictionary::new());
+    result.unwrap();
+    let index = context.find_stream(AVMEDIA_TYPE_AUDIO).unwrap();
+    let (decoder, _) = context.open_stream_copy(index, AvDictionary::new());
+    let decoder = decoder.unwrap();
+
+    // Demux on a worker, which takes the format context with it, and decode here.
+    let (sender, packets) = channel();
+    let worker = thread::spawn(move || {
+        loop {
+            let mut packet = AvPacket::empty();
+            if context.read_packet(&mut packet).is_err() {
+                break
+            }
+            sender.send(packet).unwrap();
+        }
+    });
+
+    let mut samples = 0;
+    for mut packet in packets.iter() {
+        while packet.has_data() {
+            let frame = AvFrame::new();
+            decoder.decode_audio(&frame, &mut packet).unwrap();
+            samples += frame.sample_count() as usize;
+        }
+        packet.clear();
+    }
+    worker.join().unwrap();
+
+    assert_eq!(samples, common::SAMPLES);
+}